@@ -1,16 +1,98 @@
 use chrono::prelude::*;
-use prettytable::{cell, format, row, Table};
+use prettytable::{color, format, row, Attr, Cell, Row, Table};
 use serde::{Deserialize, Serialize};
+use std::cell::RefCell;
 use std::cmp::Ordering;
-use std::collections::HashMap;
+use std::collections::{BTreeMap, BTreeSet};
+use std::convert::TryFrom;
+use std::env;
 use std::fmt;
 use std::io::prelude::*;
 use std::io::{BufReader, BufWriter};
-use std::path::Path;
+use std::path::{Path, PathBuf};
 use terminal_size::{terminal_size, Height, Width};
 use unicode_segmentation::UnicodeSegmentation;
 
 pub const DATETIME_FORMAT: &str = "%Y-%m-%d %H:%M";
+pub const DATE_FORMAT: &str = "%Y-%m-%d";
+/// Format of times in time sheets tracking seconds, also accepted wherever a time is given
+pub const DATETIME_SECONDS_FORMAT: &str = "%Y-%m-%d %H:%M:%S";
+
+/// Hours of a regular working day, used for capacity reports
+const HOURS_PER_WORKING_DAY: f32 = 8.;
+/// Width of the description column if it can't be derived from the terminal
+const DEFAULT_DESCRIPTION_WIDTH: usize = 44;
+/// Width taken by all columns of the work session table besides the description
+const FIXED_COLUMNS_WIDTH: usize = 76;
+/// Narrowest description column derived from the terminal width
+const MIN_DESCRIPTION_WIDTH: usize = 10;
+/// Decimal places of hours and amounts in `analyze` if no `--precision` is given
+const DEFAULT_PRECISION: usize = 2;
+/// Gaps between work sessions at least this long are highlighted by `analyze --gaps`
+const DEFAULT_GAP_THRESHOLD_MINUTES: u32 = 30;
+/// Working days per week assumed if a time sheet doesn't configure them
+const DEFAULT_WORKING_DAYS_PER_WEEK: u8 = 5;
+/// Seconds between checks of the time sheet for changes in `tail`
+const TAIL_POLL_SECONDS: u64 = 1;
+/// Largest distance from now a `--at` time may have without forcing it
+const MAX_AT_OFFSET_HOURS: i64 = 24;
+/// Largest offset `edit --shift` moves a work session by, one leap year
+const MAX_SHIFT_HOURS: i64 = 24 * 366;
+/// Version of the time sheet file format written by this build
+pub const SCHEMA_VERSION: u32 = 3;
+/// Date the initial hourly rate takes effect, before any other rate change
+const INITIAL_RATE_DATE: NaiveDate = chrono::naive::MIN_DATE;
+/// Longest allowed working time per day for `analyze --legal-check` if none is configured
+const DEFAULT_MAX_DAILY_HOURS: f32 = 10.;
+/// Shortest allowed rest between two working days for `analyze --legal-check` if none is configured
+const DEFAULT_MIN_REST_HOURS: f32 = 11.;
+/// Share of a day's working time that has to be spent in homeoffice to count it as homeoffice day
+/// if none is configured
+const DEFAULT_HOMEOFFICE_DAY_THRESHOLD: f32 = 0.5;
+/// Number of characters in the bar comparing the tracked time to the estimate
+const PROGRESS_BAR_WIDTH: usize = 20;
+/// Number of characters in the longest bar of the hour of day histogram
+const HISTOGRAM_WIDTH: usize = 40;
+
+/// Whether `verbose!` messages are printed, see `set_verbose`
+static VERBOSE: std::sync::atomic::AtomicBool = std::sync::atomic::AtomicBool::new(false);
+
+/// Print a debugging message to stderr if verbose output was requested.
+macro_rules! verbose {
+    ($($arg:tt)*) => {
+        if VERBOSE.load(std::sync::atomic::Ordering::Relaxed) {
+            eprintln!("[timetracker] {}", format!($($arg)*));
+        }
+    };
+}
+
+/// Turn the messages about resolving, loading, parsing and saving time sheets on stderr on or off
+pub fn set_verbose(verbose: bool) {
+    VERBOSE.store(verbose, std::sync::atomic::Ordering::Relaxed);
+}
+
+/// Ask whether to go on with a change, failing unless the answer read from `input` is yes.
+///
+/// With `assume_yes`, e.g. given by `--yes` in scripts, nothing is asked.
+fn confirm<R: BufRead>(
+    prompt: &str,
+    input: &mut R,
+    assume_yes: bool,
+) -> Result<(), TimetrackerError> {
+    if assume_yes {
+        return Ok(());
+    }
+    print!("{} [y/N] ", prompt);
+    std::io::stdout().flush()?;
+    let mut answer = String::new();
+    input.read_line(&mut answer)?;
+    match answer.trim().to_lowercase().as_str() {
+        "y" | "yes" => Ok(()),
+        _ => Err(TimetrackerError::TimeSheet(String::from(
+            "Aborted, nothing was changed.",
+        ))),
+    }
+}
 
 /// A enum to represent possible errors within a timetracker
 #[derive(Debug)]
@@ -21,10 +103,30 @@ pub enum TimetrackerError {
     SerdeJSON(String),
     ChronoParse(String),
     TimeSheet(String),
+    Csv(String),
+    Toml(String),
 }
 
 impl std::error::Error for TimetrackerError {}
 
+impl TimetrackerError {
+    /// Exit code of the command line tool for this kind of error.
+    ///
+    /// 1 is left to argument parsing errors, every variant gets its own code from 2 on.
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            TimetrackerError::IOError(_) => 2,
+            TimetrackerError::TimeSheet(_) => 3,
+            TimetrackerError::SerdeJSON(_) => 4,
+            TimetrackerError::ChronoParse(_) => 5,
+            TimetrackerError::Subproject(_) => 6,
+            TimetrackerError::Activity(_) => 7,
+            TimetrackerError::Csv(_) => 8,
+            TimetrackerError::Toml(_) => 9,
+        }
+    }
+}
+
 impl fmt::Display for TimetrackerError {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         match self {
@@ -34,6 +136,8 @@ impl fmt::Display for TimetrackerError {
             TimetrackerError::SerdeJSON(e) => write!(f, "Serde JSON Error: {}", e),
             TimetrackerError::ChronoParse(e) => write!(f, "Chrono Parse Error: {}", e),
             TimetrackerError::TimeSheet(e) => write!(f, "TimeSheet Error: {}", e),
+            TimetrackerError::Csv(e) => write!(f, "CSV Error: {}", e),
+            TimetrackerError::Toml(e) => write!(f, "TOML Error: {}", e),
         }
     }
 }
@@ -56,11 +160,56 @@ impl From<chrono::ParseError> for TimetrackerError {
     }
 }
 
-#[derive(Serialize, Deserialize, Eq, Debug)]
-struct SubProject {
+impl From<csv::Error> for TimetrackerError {
+    fn from(error: csv::Error) -> Self {
+        TimetrackerError::Csv(error.to_string())
+    }
+}
+
+impl From<toml::de::Error> for TimetrackerError {
+    fn from(error: toml::de::Error) -> Self {
+        TimetrackerError::Toml(error.to_string())
+    }
+}
+
+impl From<toml::ser::Error> for TimetrackerError {
+    fn from(error: toml::ser::Error) -> Self {
+        TimetrackerError::Toml(error.to_string())
+    }
+}
+
+/// File formats a time sheet can be stored in
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum StorageFormat {
+    Json,
+    Toml,
+}
+
+impl StorageFormat {
+    /// Pick the format by the file extension, defaulting to JSON.
+    fn of(path: &Path) -> StorageFormat {
+        match path.extension().and_then(|e| e.to_str()) {
+            Some(e) if e.eq_ignore_ascii_case("toml") => StorageFormat::Toml,
+            _ => StorageFormat::Json,
+        }
+    }
+}
+
+/// A subproject within a time sheet.
+///
+/// The fields are private; `id`, `name` and `description` are part of the stable API through
+/// their accessor methods. Subprojects can be nested by referencing a `parent`.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct SubProject {
     id: usize,
     name: String,
     description: String,
+    #[serde(default)]
+    parent: Option<usize>,
+    /// Rate for work sessions assigned to this subproject or its children, overriding the
+    /// project's rate
+    #[serde(default)]
+    hourly_rate: Option<f32>,
 }
 
 impl PartialEq for SubProject {
@@ -69,6 +218,8 @@ impl PartialEq for SubProject {
     }
 }
 
+impl Eq for SubProject {}
+
 impl Ord for SubProject {
     fn cmp(&self, other: &Self) -> Ordering {
         self.id.cmp(&other.id)
@@ -77,27 +228,223 @@ impl Ord for SubProject {
 
 impl PartialOrd for SubProject {
     fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
-        Some(self.cmp(&other))
+        Some(self.cmp(other))
     }
 }
 
 impl SubProject {
-    fn new(id: usize, name: String, description: String) -> SubProject {
+    pub fn new(id: usize, name: String, description: String) -> SubProject {
         SubProject {
             id,
             name,
             description,
+            parent: None,
+            hourly_rate: None,
         }
     }
+
+    pub fn id(&self) -> usize {
+        self.id
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn description(&self) -> &str {
+        &self.description
+    }
+
+    /// ID of the subproject this one is nested in
+    pub fn parent(&self) -> Option<usize> {
+        self.parent
+    }
+
+    pub fn hourly_rate(&self) -> Option<f32> {
+        self.hourly_rate
+    }
 }
 
+/// An activity work sessions within a time sheet can be assigned to.
+///
+/// The fields are private; `id`, `name` and `description` are part of the stable API through
+/// their accessor methods.
 #[derive(Serialize, Deserialize, Eq, Debug)]
-struct WorkSession {
+pub struct Activity {
+    id: usize,
+    name: String,
+    description: String,
+}
+
+impl PartialEq for Activity {
+    fn eq(&self, other: &Self) -> bool {
+        self.name == other.name && self.description == other.description && self.id == other.id
+    }
+}
+
+impl Ord for Activity {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.id.cmp(&other.id)
+    }
+}
+
+impl PartialOrd for Activity {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Activity {
+    pub fn new(id: usize, name: String, description: String) -> Activity {
+        Activity {
+            id,
+            name,
+            description,
+        }
+    }
+
+    pub fn id(&self) -> usize {
+        self.id
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn description(&self) -> &str {
+        &self.description
+    }
+}
+
+/// Money spent for a project, like travel costs, billed on top of the tracked time.
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug)]
+pub struct Expense {
+    date: NaiveDate,
+    description: String,
+    amount: f32,
+}
+
+impl Expense {
+    pub fn new(date: NaiveDate, description: String, amount: f32) -> Expense {
+        Expense {
+            date,
+            description,
+            amount,
+        }
+    }
+
+    pub fn date(&self) -> NaiveDate {
+        self.date
+    }
+
+    pub fn description(&self) -> &str {
+        &self.description
+    }
+
+    pub fn amount(&self) -> f32 {
+        self.amount
+    }
+}
+
+/// A single span of tracked work.
+///
+/// The fields are private; `start`, `stop`, `description` and `homeoffice` are part of the stable
+/// API through their accessor methods. A session without a stop time is still running.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct WorkSession {
+    /// Stable ID, unlike the position in the time sheet it never changes
+    #[serde(default)]
+    id: usize,
+    #[serde(with = "rfc3339")]
     start: DateTime<Local>,
+    #[serde(default, with = "rfc3339_option")]
     stop: Option<DateTime<Local>>,
     description: String,
     #[serde(default)]
     homeoffice: bool,
+    #[serde(default)]
+    activity: Option<usize>,
+    #[serde(default)]
+    subproject: Option<usize>,
+    #[serde(default = "default_billable")]
+    billable: bool,
+    /// Flat fee billed on top of the hourly cost
+    #[serde(default)]
+    fixed_fee: Option<f32>,
+    #[serde(default)]
+    tags: Vec<String>,
+    /// Whether the work session was billed by an invoice
+    #[serde(default)]
+    invoiced: bool,
+}
+
+fn default_billable() -> bool {
+    true
+}
+
+/// Parse a time given as `DATETIME_FORMAT` or `DATETIME_SECONDS_FORMAT` in local time.
+pub fn parse_datetime(s: &str) -> Result<DateTime<Local>, chrono::ParseError> {
+    Local.datetime_from_str(s, DATETIME_FORMAT).or_else(|e| {
+        Local
+            .datetime_from_str(s, DATETIME_SECONDS_FORMAT)
+            .map_err(|_| e)
+    })
+}
+
+/// Parse a stored time as RFC 3339, falling back to `DATETIME_FORMAT` in local time as found in
+/// hand-edited time sheets.
+fn parse_stored_time(s: &str) -> Result<DateTime<Local>, chrono::ParseError> {
+    match DateTime::parse_from_rfc3339(s) {
+        Ok(time) => Ok(time.with_timezone(&Local)),
+        Err(e) => Local.datetime_from_str(s, DATETIME_FORMAT).map_err(|_| e),
+    }
+}
+
+/// (De)serialization of times as RFC 3339 strings including their UTC offset.
+mod rfc3339 {
+    use chrono::{DateTime, Local, SecondsFormat};
+    use serde::{de, Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(
+        time: &DateTime<Local>,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&time.to_rfc3339_opts(SecondsFormat::AutoSi, false))
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<DateTime<Local>, D::Error> {
+        let s = String::deserialize(deserializer)?;
+        super::parse_stored_time(&s).map_err(de::Error::custom)
+    }
+}
+
+/// Like `rfc3339`, for times that might not be set.
+mod rfc3339_option {
+    use chrono::{DateTime, Local};
+    use serde::{de, Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(
+        time: &Option<DateTime<Local>>,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        match time {
+            Some(t) => super::rfc3339::serialize(t, serializer),
+            None => serializer.serialize_none(),
+        }
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Option<DateTime<Local>>, D::Error> {
+        match Option::<String>::deserialize(deserializer)? {
+            Some(s) => super::parse_stored_time(&s)
+                .map(Some)
+                .map_err(de::Error::custom),
+            None => Ok(None),
+        }
+    }
 }
 
 impl PartialEq for WorkSession {
@@ -108,6 +455,8 @@ impl PartialEq for WorkSession {
     }
 }
 
+impl Eq for WorkSession {}
+
 impl Ord for WorkSession {
     fn cmp(&self, other: &Self) -> Ordering {
         self.start.cmp(&other.start)
@@ -116,22 +465,29 @@ impl Ord for WorkSession {
 
 impl PartialOrd for WorkSession {
     fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
-        Some(self.cmp(&other))
+        Some(self.cmp(other))
     }
 }
 
 impl WorkSession {
-    fn new(
+    pub fn new(
         start: DateTime<Local>,
         stop: Option<DateTime<Local>>,
         description: String,
         homeoffice: bool,
     ) -> WorkSession {
         WorkSession {
+            id: 0,
             start,
             stop,
             description,
             homeoffice,
+            activity: None,
+            subproject: None,
+            billable: true,
+            fixed_fee: None,
+            tags: Vec::new(),
+            invoiced: false,
         }
     }
 
@@ -141,10 +497,96 @@ impl WorkSession {
         homeoffice: bool,
     ) -> WorkSession {
         WorkSession {
+            id: 0,
             start,
             description,
             homeoffice,
             stop: None,
+            activity: None,
+            subproject: None,
+            billable: true,
+            fixed_fee: None,
+            tags: Vec::new(),
+            invoiced: false,
+        }
+    }
+
+    /// ID of the work session, assigned when it is added to a time sheet
+    pub fn id(&self) -> usize {
+        self.id
+    }
+
+    pub fn start(&self) -> DateTime<Local> {
+        self.start
+    }
+
+    pub fn stop(&self) -> Option<DateTime<Local>> {
+        self.stop
+    }
+
+    pub fn description(&self) -> &str {
+        &self.description
+    }
+
+    pub fn homeoffice(&self) -> bool {
+        self.homeoffice
+    }
+
+    /// ID of the activity this work session is assigned to
+    pub fn activity(&self) -> Option<usize> {
+        self.activity
+    }
+
+    /// ID of the subproject this work session is assigned to
+    pub fn subproject(&self) -> Option<usize> {
+        self.subproject
+    }
+
+    /// Whether the work session is billed to the client.
+    pub fn billable(&self) -> bool {
+        self.billable
+    }
+
+    pub fn fixed_fee(&self) -> Option<f32> {
+        self.fixed_fee
+    }
+
+    /// Free-form labels of the work session
+    pub fn tags(&self) -> &[String] {
+        &self.tags
+    }
+
+    /// Time elapsed in this work session, counting a running session up to `now`.
+    pub fn duration(&self, now: DateTime<Local>) -> chrono::Duration {
+        self.stop.unwrap_or(now) - self.start
+    }
+
+    /// All calendar days this work session touches, counting a running session up to `now`.
+    ///
+    /// A session stopping exactly at midnight does not touch the following day.
+    fn dates(&self, now: DateTime<Local>) -> Vec<Date<Local>> {
+        let stop = self.stop.unwrap_or(now);
+        let mut last_date = stop.date();
+        if stop > self.start && stop.time() == NaiveTime::from_hms(0, 0, 0) {
+            last_date = last_date.pred();
+        }
+        let mut dates = vec![self.start.date()];
+        while *dates.last().unwrap() < last_date {
+            dates.push(dates.last().unwrap().succ());
+        }
+        dates
+    }
+
+    /// The part of this work session falling on `date`, counting a running session up to `now`.
+    fn duration_on(&self, date: Date<Local>, now: DateTime<Local>) -> chrono::Duration {
+        let day_start = date.and_hms(0, 0, 0);
+        let day_stop = date.succ().and_hms(0, 0, 0);
+        let start = self.start.max(day_start);
+        let stop = self.stop.unwrap_or(now).min(day_stop);
+        if stop > start {
+            stop - start
+        } else {
+            chrono::Duration::zero()
         }
     }
 }
@@ -159,22 +601,173 @@ impl proptest::arbitrary::Arbitrary for WorkSession {
 }
 */
 
+/// How work session durations are rounded to the billing increment
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum RoundingMode {
+    /// Bill every started increment
+    Up,
+    /// Only bill completed increments
+    Down,
+    /// Round to the closest increment, with halves rounding up
+    Nearest,
+}
+
+impl Default for RoundingMode {
+    fn default() -> Self {
+        RoundingMode::Up
+    }
+}
+
+/// Round a duration to a multiple of `increment` minutes, dropping sub-second precision.
+fn round_duration(
+    duration: chrono::Duration,
+    increment: u32,
+    mode: RoundingMode,
+) -> chrono::Duration {
+    let seconds = duration.num_seconds();
+    let increment = i64::from(increment.max(1)) * 60;
+    let increments = match mode {
+        RoundingMode::Up => (seconds + increment - 1).div_euclid(increment),
+        RoundingMode::Down => seconds.div_euclid(increment),
+        RoundingMode::Nearest => (seconds + increment / 2).div_euclid(increment),
+    };
+    chrono::Duration::seconds(increments * increment)
+}
+
+/// A textual bar like `[#####---] 62%` showing `tracked` against `estimated`.
+///
+/// The bar is capped at full, the percentage keeps counting beyond 100%.
+fn progress_bar(tracked: f32, estimated: f32) -> String {
+    let fraction = if estimated > 0. {
+        tracked / estimated
+    } else {
+        0.
+    };
+    let filled = ((fraction.clamp(0., 1.) * PROGRESS_BAR_WIDTH as f32).round()) as usize;
+    format!(
+        "[{}{}] {:.0}%",
+        "#".repeat(filled),
+        "-".repeat(PROGRESS_BAR_WIDTH - filled),
+        fraction * 100.
+    )
+}
+
+/// The time sheet of a project, as stored in `time_sheet.json`.
+///
+/// The fields are private; `project_name`, `hourly_rate`, `work_sessions` and `subprojects` are
+/// part of the stable API through their accessor methods. Work sessions are always kept sorted
+/// by their start time, which is why they can only be added through `add_work_session`.
 #[derive(Serialize, Deserialize, PartialEq, Debug)]
-struct TimeSheet {
+pub struct TimeSheet {
     project_name: String,
+    /// Hourly rate of time sheets before schema version 3, moved into `rate_changes` on load
+    #[serde(default, skip_serializing)]
     hourly_rate: Option<f32>,
     work_sessions: Vec<WorkSession>,
     #[serde(default)]
     subprojects: Vec<SubProject>,
+    #[serde(default)]
+    activities: Vec<Activity>,
+    #[serde(default)]
+    holidays: Vec<NaiveDate>,
+    #[serde(default)]
+    working_days_per_week: Option<u8>,
+    /// Work sessions shorter than this can't be stopped
+    #[serde(default)]
+    minimum_session_minutes: Option<u32>,
+    /// Changes of the hourly rate, sorted by the date they take effect. The initial rate takes
+    /// effect on `INITIAL_RATE_DATE`, before the first change there is no rate.
+    #[serde(default)]
+    rate_changes: Vec<(NaiveDate, f32)>,
+    /// Version of the file format, 0 for time sheets created before it was versioned
+    #[serde(default)]
+    schema_version: u32,
+    /// Total amount the project may cost
+    #[serde(default)]
+    budget: Option<f32>,
+    /// Bill work sessions in multiples of this many minutes
+    #[serde(default)]
+    billing_increment_minutes: Option<u32>,
+    #[serde(default)]
+    rounding_mode: Option<RoundingMode>,
+    /// ID the next new work session gets
+    #[serde(default)]
+    next_session_id: usize,
+    /// Running work sessions longer than this were most likely forgotten to be stopped
+    #[serde(default)]
+    auto_stop_after_hours: Option<f32>,
+    /// Free-form notes about the whole project
+    #[serde(default)]
+    notes: String,
+    /// Hours the project is expected to take
+    #[serde(default)]
+    estimated_hours: Option<f32>,
+    /// Date up to which work sessions have been invoiced
+    #[serde(default)]
+    last_invoice_date: Option<NaiveDate>,
+    /// Whether new work sessions are done in homeoffice unless stated otherwise
+    #[serde(default)]
+    default_homeoffice: Option<bool>,
+    /// Longest allowed working time per day
+    #[serde(default)]
+    max_daily_hours: Option<f32>,
+    /// Shortest allowed rest between the last work session of a day and the first of the next
+    #[serde(default)]
+    min_rest_hours: Option<f32>,
+    /// A day counts as homeoffice day if more than this share of its working time was spent in
+    /// homeoffice
+    #[serde(default)]
+    homeoffice_day_threshold: Option<f32>,
+    /// Keep the seconds of start and stop times and show durations with seconds.
+    ///
+    /// Meant for tracking tasks shorter than a minute. Totals and costs then rarely come out as
+    /// round numbers, so billing users are better off with the default minute precision.
+    #[serde(default)]
+    seconds_precision: Option<bool>,
+    /// Flat sum billed for the whole project, replacing the hourly costs in the totals
+    #[serde(default)]
+    fixed_price: Option<f32>,
+    /// Expenses billed on top of the tracked time, sorted by date
+    #[serde(default)]
+    expenses: Vec<Expense>,
+    /// Fallbacks from the global configuration for settings the time sheet leaves open
+    #[serde(skip)]
+    defaults: GlobalConfig,
 }
 
 impl TimeSheet {
-    fn new(project_name: String, hourly_rate: Option<f32>) -> TimeSheet {
+    pub fn new(project_name: String, hourly_rate: Option<f32>) -> TimeSheet {
         TimeSheet {
             project_name,
-            hourly_rate,
+            hourly_rate: None,
             work_sessions: Vec::new(),
             subprojects: Vec::new(),
+            activities: Vec::new(),
+            holidays: Vec::new(),
+            working_days_per_week: None,
+            minimum_session_minutes: None,
+            rate_changes: hourly_rate
+                .map(|r| (INITIAL_RATE_DATE, r))
+                .into_iter()
+                .collect(),
+            schema_version: SCHEMA_VERSION,
+            budget: None,
+            billing_increment_minutes: None,
+            rounding_mode: None,
+            next_session_id: 0,
+            auto_stop_after_hours: None,
+            notes: String::new(),
+            estimated_hours: None,
+            last_invoice_date: None,
+            default_homeoffice: None,
+            max_daily_hours: None,
+            min_rest_hours: None,
+            homeoffice_day_threshold: None,
+            seconds_precision: None,
+            fixed_price: None,
+            expenses: Vec::new(),
+            defaults: GlobalConfig::default(),
         }
     }
 
@@ -182,366 +775,5868 @@ impl TimeSheet {
         serde_json::from_str(&json_string)
     }
 
-    fn load(path: &Path) -> Result<TimeSheet, TimetrackerError> {
-        let file = std::fs::File::open(&path)?;
+    /// Load a time sheet from a JSON file, or a TOML file if its extension is `.toml`.
+    pub fn load(path: &Path) -> Result<TimeSheet, TimetrackerError> {
+        verbose!("Loading time sheet from {}", path.display());
+        let file = match std::fs::File::open(path) {
+            Ok(f) => f,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+                return Err(TimetrackerError::IOError(format!(
+                    "No time sheet found at {}. Run `init` to create a new project first.",
+                    path.display()
+                )));
+            }
+            Err(e) => return Err(e.into()),
+        };
         let reader = BufReader::new(&file);
         let mut lines = vec![];
         for line in reader.lines() {
             lines.push(line?);
         }
-        let json_string = lines.join("\n");
-        Ok(TimeSheet::from_json(json_string)?)
+        let content = lines.join("\n");
+        let format = StorageFormat::of(path);
+        verbose!("Parsing {} lines as {:?}", lines.len(), format);
+        let mut time_sheet = match format {
+            StorageFormat::Json => TimeSheet::from_json(content)?,
+            StorageFormat::Toml => toml::from_str(&content)?,
+        };
+        verbose!(
+            "Parsed project {} with {} work sessions, schema version {}",
+            time_sheet.project_name,
+            time_sheet.work_sessions.len(),
+            time_sheet.schema_version
+        );
+        time_sheet.defaults = global_config();
+        if time_sheet.next_session_id == 0 {
+            verbose!(
+                "Numbering {} work sessions by their position",
+                time_sheet.work_sessions.len()
+            );
+            // Time sheets from before stable IDs identified work sessions by their position
+            for (id, work_session) in time_sheet.work_sessions.iter_mut().enumerate() {
+                work_session.id = id;
+            }
+            time_sheet.next_session_id = time_sheet.work_sessions.len();
+        }
+        if time_sheet.schema_version < 2 {
+            // Invoices used to cover all work sessions started up to the last invoice date
+            if let Some(last) = time_sheet.last_invoice_date {
+                for work_session in time_sheet.work_sessions.iter_mut() {
+                    work_session.invoiced = work_session.billable
+                        && work_session.stop.is_some()
+                        && work_session.start.naive_local().date() <= last;
+                }
+            }
+        }
+        if let Some(r) = time_sheet.hourly_rate.take() {
+            verbose!("Moving the hourly rate {} into the rate changes", r);
+            time_sheet.change_rate(INITIAL_RATE_DATE, r);
+        }
+        time_sheet.schema_version = time_sheet.schema_version.max(SCHEMA_VERSION);
+        Ok(time_sheet)
     }
 }
 
 impl TimeSheet {
-    fn to_json(&self) -> serde_json::Result<String> {
-        serde_json::to_string(&self)
+    pub fn project_name(&self) -> &str {
+        &self.project_name
     }
 
-    fn save(&self, path: &Path) -> Result<(), TimetrackerError> {
-        let file = std::fs::File::create(&path)?;
-        let mut writer = BufWriter::new(&file);
-        write!(&mut writer, "{}", &self.to_json()?)?;
-        Ok(())
+    /// The hourly rate in effect today.
+    pub fn hourly_rate(&self) -> Option<f32> {
+        self.rate_at(Local::now().naive_local().date())
     }
-}
 
-fn split_description_string(desc_string: &str, max_line_length: usize) -> String {
-    let desc_split = desc_string.split(' ');
-    let mut lines_vec = vec![];
-    let mut line_vec = vec![];
-    for word in desc_split {
-        if line_vec.join(" ").graphemes(true).count() + word.graphemes(true).count()
-            > max_line_length
-        {
-            lines_vec.push(line_vec.join(" "));
-            line_vec.clear();
-        }
-        line_vec.push(word);
+    /// All work sessions, sorted by their start time.
+    pub fn work_sessions(&self) -> &[WorkSession] {
+        &self.work_sessions
     }
-    lines_vec.push(line_vec.join(" "));
-    lines_vec.join("\n")
-}
 
-pub fn initialize_project(
-    name: String,
-    hourly_rate: Option<f32>,
-    path: &Path,
-) -> Result<(), TimetrackerError> {
-    println!(
-        "Initializing Project {} with an hourly rate of {:.02}€",
-        name,
-        hourly_rate.unwrap_or(0f32)
-    );
-    let time_sheet = TimeSheet::new(name, hourly_rate);
-    time_sheet.save(path)?;
-    Ok(())
-}
+    /// How work sessions are rounded to the billing increment, from the time sheet or the global
+    /// configuration.
+    fn rounding_mode(&self) -> RoundingMode {
+        self.rounding_mode
+            .or(self.defaults.rounding_mode)
+            .unwrap_or_default()
+    }
 
-pub fn start_working_session(
-    description: Option<&str>,
-    homeoffice: bool,
-) -> Result<(), TimetrackerError> {
-    let start_time = Local::now();
-    let mut desc = String::new();
-    let path = Path::new("time_sheet.json");
-    let mut time_sheet = TimeSheet::load(&path)?;
-    if let Some(s) = time_sheet.work_sessions.last() {
-        match s.stop {
-            None => {
-                return Err(TimetrackerError::TimeSheet(String::from(
-                    "Last work session not finished!",
-                )));
-            }
-            Some(_) => (),
-        }
-    };
-    match description {
-        Some(d) => {
-            desc.push_str(d);
-            println!(
-                "Start working on {} at {}",
-                desc,
-                start_time.format(DATETIME_FORMAT)
-            );
+    /// Whether new work sessions are done in homeoffice, from the time sheet or the global
+    /// configuration.
+    fn default_homeoffice(&self) -> bool {
+        self.default_homeoffice
+            .or(self.defaults.default_homeoffice)
+            .unwrap_or(false)
+    }
+
+    /// Whether start and stop times are tracked to the second, from the time sheet or the global
+    /// configuration.
+    fn seconds_precision(&self) -> bool {
+        self.seconds_precision
+            .or(self.defaults.seconds_precision)
+            .unwrap_or(false)
+    }
+
+    /// Format to show start and stop times in.
+    ///
+    /// That is the global `datetime_format` if there is one, otherwise the default format with
+    /// seconds if the time sheet tracks them.
+    pub fn datetime_format(&self) -> &str {
+        match &self.defaults.datetime_format {
+            Some(format) => format,
+            None if self.seconds_precision() => DATETIME_SECONDS_FORMAT,
+            None => DATETIME_FORMAT,
         }
-        None => println!("Start working at {}", start_time.format(DATETIME_FORMAT)),
-    };
-    time_sheet
-        .work_sessions
-        .push(WorkSession::start_new_work_session(
-            start_time, desc, homeoffice,
-        ));
-    time_sheet.save(&path)?;
-    Ok(())
-}
+    }
 
-pub fn stop_working_session(
-    description: Option<&str>,
-    homeoffice: bool,
-) -> Result<(), TimetrackerError> {
-    let stop_time = Local::now();
-    let mut desc = String::new();
-    let path = Path::new("time_sheet.json");
-    let mut time_sheet = TimeSheet::load(&path).unwrap();
-    match time_sheet.work_sessions.last() {
-        Some(s) => match s.stop {
-            None => (),
-            Some(_) => {
-                return Err(TimetrackerError::TimeSheet(String::from(
-                    "No unfinished work session found to stop!",
+    /// All work sessions overlapping the range from `from` (inclusive) to `to` (exclusive).
+    ///
+    /// A work session stopping exactly at `from` or starting exactly at `to` is not part of the
+    /// range, while one starting exactly at `from` is, even if it has no duration. Running work
+    /// sessions count as going on indefinitely, so they are included if they started before `to`.
+    pub fn sessions_in_range(
+        &self,
+        from: DateTime<Local>,
+        to: DateTime<Local>,
+    ) -> Vec<&WorkSession> {
+        self.work_sessions
+            .iter()
+            .filter(|work_session| {
+                work_session.start < to
+                    && (work_session.start >= from
+                        || work_session.stop.map_or(true, |stop| stop > from))
+            })
+            .collect()
+    }
+
+    pub fn expenses(&self) -> &[Expense] {
+        &self.expenses
+    }
+
+    pub fn subprojects(&self) -> &[SubProject] {
+        &self.subprojects
+    }
+
+    pub fn activities(&self) -> &[Activity] {
+        &self.activities
+    }
+
+    /// Make sure an activity with the given ID exists.
+    fn check_activity(&self, id: usize) -> Result<(), TimetrackerError> {
+        if self.activities.iter().any(|a| a.id == id) {
+            Ok(())
+        } else {
+            Err(TimetrackerError::Activity(format!(
+                "No activity with ID {}!",
+                id
+            )))
+        }
+    }
+
+    /// Make sure a subproject with the given ID exists.
+    fn check_subproject(&self, id: usize) -> Result<(), TimetrackerError> {
+        if self.subprojects.iter().any(|s| s.id == id) {
+            Ok(())
+        } else {
+            Err(TimetrackerError::Subproject(format!(
+                "No subproject with ID {}!",
+                id
+            )))
+        }
+    }
+
+    /// Trim a subproject name and make sure no other subproject is called the same.
+    ///
+    /// Names are compared case-insensitively, the subproject `id` itself is ignored when renaming.
+    fn check_subproject_name(
+        &self,
+        name: &str,
+        id: Option<usize>,
+    ) -> Result<String, TimetrackerError> {
+        let name = name.trim();
+        if name.is_empty() {
+            return Err(TimetrackerError::Subproject(String::from(
+                "Subproject name can't be empty!",
+            )));
+        }
+        if let Some(existing) = self
+            .subprojects
+            .iter()
+            .find(|s| Some(s.id) != id && s.name.trim().to_lowercase() == name.to_lowercase())
+        {
+            return Err(TimetrackerError::Subproject(format!(
+                "There already is a subproject {} with ID {}!",
+                existing.name, existing.id
+            )));
+        }
+        Ok(name.to_string())
+    }
+
+    /// The given subproject followed by all subprojects it is nested in.
+    ///
+    /// Stops at the first repeated ID, so a corrupted sheet can't loop forever.
+    fn subproject_ancestors(&self, id: usize) -> Vec<usize> {
+        let mut ancestors = vec![id];
+        let mut current = id;
+        while let Some(parent) = self
+            .subprojects
+            .iter()
+            .find(|s| s.id == current)
+            .and_then(|s| s.parent)
+        {
+            if ancestors.contains(&parent) {
+                break;
+            }
+            ancestors.push(parent);
+            current = parent;
+        }
+        ancestors
+    }
+
+    /// Nest a subproject into `parent`, or make it a top level subproject again with `None`.
+    fn set_subproject_parent(
+        &mut self,
+        id: usize,
+        parent: Option<usize>,
+    ) -> Result<(), TimetrackerError> {
+        self.check_subproject(id)?;
+        if let Some(p) = parent {
+            if p == id {
+                return Err(TimetrackerError::Subproject(format!(
+                    "Subproject {} can't be its own parent!",
+                    id
+                )));
+            }
+            self.check_subproject(p)?;
+            if self.subproject_ancestors(p).contains(&id) {
+                return Err(TimetrackerError::Subproject(format!(
+                    "Nesting subproject {} into {} would create a cycle!",
+                    id, p
                 )));
             }
-        },
-        None => {
-            return Err(TimetrackerError::TimeSheet(String::from(
-                "No unfinished work session found to stop!",
-            )));
         }
+        if let Some(subproject) = self.subprojects.iter_mut().find(|s| s.id == id) {
+            subproject.parent = parent;
+        }
+        Ok(())
     }
-    match description {
-        Some(d) => {
-            desc.push_str(d);
-            println!(
-                "Stop working on {} at {}",
-                desc,
-                stop_time.format(DATETIME_FORMAT)
-            );
+
+    /// Give the subprojects the IDs 0 to n in their current order and update all references.
+    ///
+    /// References to a duplicated ID go to its first subproject, references to unknown IDs are
+    /// removed. Returns a description of every change.
+    fn renumber_subprojects(&mut self) -> Vec<String> {
+        let mut changes = Vec::new();
+        let mut new_ids: BTreeMap<usize, usize> = BTreeMap::new();
+        for (new_id, subproject) in self.subprojects.iter_mut().enumerate() {
+            if subproject.id != new_id {
+                changes.push(format!(
+                    "Subproject {} now has ID {} instead of {}",
+                    subproject.name, new_id, subproject.id
+                ));
+            }
+            new_ids.entry(subproject.id).or_insert(new_id);
+            subproject.id = new_id;
+        }
+        for subproject in &mut self.subprojects {
+            if let Some(parent) = subproject.parent {
+                subproject.parent = new_ids.get(&parent).copied();
+                if subproject.parent.is_none() {
+                    changes.push(format!(
+                        "Subproject {} lost its unknown parent {}",
+                        subproject.name, parent
+                    ));
+                }
+            }
+        }
+        for work_session in &mut self.work_sessions {
+            if let Some(subproject) = work_session.subproject {
+                work_session.subproject = new_ids.get(&subproject).copied();
+                if work_session.subproject.is_none() {
+                    changes.push(format!(
+                        "Work session {} lost its unknown subproject {}",
+                        work_session.id, subproject
+                    ));
+                }
+            }
         }
-        None => println!("Stop working at {}", stop_time.format(DATETIME_FORMAT)),
+        changes
     }
-    //time_sheet.work_sessions.last().unwrap().stop = Some(stop_time);
-    let mut last_work_session = time_sheet.work_sessions.pop().unwrap();
-    last_work_session.stop = Some(stop_time);
-    if description.is_some() {
-        last_work_session.description = desc;
+
+    /// Time and cost per subproject, including everything tracked on nested subprojects.
+    fn subproject_totals(
+        &self,
+        now: DateTime<Local>,
+    ) -> BTreeMap<usize, (chrono::Duration, Option<f32>)> {
+        let mut sessions: BTreeMap<usize, Vec<&WorkSession>> = BTreeMap::new();
+        for work_session in &self.work_sessions {
+            if let Some(id) = work_session.subproject {
+                for ancestor in self.subproject_ancestors(id) {
+                    sessions.entry(ancestor).or_default().push(work_session);
+                }
+            }
+        }
+        sessions
+            .into_iter()
+            .map(|(id, sessions)| {
+                let duration = sessions.iter().fold(chrono::Duration::zero(), |total, ws| {
+                    total + ws.duration(now)
+                });
+                (id, (duration, self.cost_of(sessions, now)))
+            })
+            .collect()
     }
-    if homeoffice {
-        last_work_session.homeoffice = homeoffice;
+
+    /// Time tracked per activity, with `None` collecting all unassigned work sessions.
+    fn activity_durations(
+        &self,
+        now: DateTime<Local>,
+    ) -> BTreeMap<Option<usize>, chrono::Duration> {
+        let mut activity_map: BTreeMap<Option<usize>, chrono::Duration> = BTreeMap::new();
+        for work_session in &self.work_sessions {
+            let total = activity_map
+                .entry(work_session.activity)
+                .or_insert_with(chrono::Duration::zero);
+            *total = *total + work_session.duration(now);
+        }
+        activity_map
     }
-    time_sheet.work_sessions.push(last_work_session);
-    time_sheet.save(&path).unwrap();
-    Ok(())
-}
 
-/// Switch from one working session to the next.
-pub fn switch_working_sessions(
-    description: Option<&str>,
-    homeoffice: bool,
-) -> Result<(), TimetrackerError> {
-    stop_working_session(description, homeoffice)?;
-    start_working_session(None, homeoffice)
-}
+    fn get_activity_mut(&mut self, id: usize) -> Result<&mut Activity, TimetrackerError> {
+        self.activities
+            .iter_mut()
+            .find(|a| a.id == id)
+            .ok_or_else(|| TimetrackerError::Activity(format!("No activity with ID {}!", id)))
+    }
 
-pub fn analyze_work_sheet(_project: Option<&str>) -> Result<(), TimetrackerError> {
-    let path = Path::new("time_sheet.json");
-    let time_sheet = TimeSheet::load(&path)?;
-    let mut work_time: f32 = 0.;
-    let mut project_cost: f32 = 0.;
+    /// Total time tracked in all work sessions, counting running sessions up to now.
+    pub fn total_duration(&self) -> chrono::Duration {
+        let now = Local::now();
+        self.work_sessions
+            .iter()
+            .fold(chrono::Duration::zero(), |total, work_session| {
+                total + work_session.duration(now)
+            })
+    }
 
-    let mut project_table = Table::new();
-    project_table.add_row(row!["Project", time_sheet.project_name]);
-    if let Some(r) = time_sheet.hourly_rate {
-        project_table.add_row(row!["Hourly Rate", r->format!("{:.02}€", r)]);
+    /// Whether the project or any subproject has an hourly rate at any point in time or any
+    /// fixed fee.
+    fn has_costs(&self) -> bool {
+        !self.rate_changes.is_empty()
+            || self.subprojects.iter().any(|s| s.hourly_rate.is_some())
+            || self.work_sessions.iter().any(|w| w.fixed_fee.is_some())
     }
-    project_table.printstd();
 
-    println!();
+    /// The subproject whose rate applies to work sessions of the given subproject.
+    ///
+    /// That is the subproject itself if it has a rate, otherwise the closest parent with one.
+    /// `None` means the project's rate applies.
+    fn rate_subproject(&self, id: usize) -> Option<&SubProject> {
+        let mut current = self.subprojects.iter().find(|s| s.id == id);
+        // Bounded by the number of subprojects in case of a broken parent cycle
+        for _ in 0..self.subprojects.len() {
+            let subproject = current?;
+            if subproject.hourly_rate.is_some() {
+                return Some(subproject);
+            }
+            current = subproject
+                .parent
+                .and_then(|parent| self.subprojects.iter().find(|s| s.id == parent));
+        }
+        None
+    }
 
-    let mut table = Table::new();
-    table.set_format(*format::consts::FORMAT_NO_BORDER_LINE_SEPARATOR);
+    /// Index of the rate period a date falls into, 0 being the period before the initial rate.
+    fn rate_period(&self, date: NaiveDate) -> usize {
+        self.rate_changes
+            .iter()
+            .take_while(|(since, _)| *since <= date)
+            .count()
+    }
 
-    let mut homeoffice_map: HashMap<String, Vec<Date<Local>>> = HashMap::new();
+    fn period_rate(&self, period: usize) -> Option<f32> {
+        period.checked_sub(1).map(|p| self.rate_changes[p].1)
+    }
 
-    match time_sheet.hourly_rate {
-        Some(_) => table.set_titles(row![
-            "ID",
-            "Start",
-            "Stop",
-            "H",
-            "Time [h]",
-            "Cost [€]",
-            "Description"
-        ]),
-        None => table.set_titles(row!["ID", "Start", "Stop", "H", "Time [h]", "Description"]),
-    }
-
-    for (i, work_session) in time_sheet.work_sessions.iter().enumerate() {
-        let width = match terminal_size() {
-            Some((Width(w), Height(_))) => {
-                if w > 78 {
-                    w
-                } else {
-                    78
-                }
+    /// The hourly rate in effect on the given date.
+    pub fn rate_at(&self, date: NaiveDate) -> Option<f32> {
+        self.period_rate(self.rate_period(date))
+    }
+
+    /// Set the hourly rate in effect from the given date on, replacing a change on the same date.
+    fn change_rate(&mut self, since: NaiveDate, rate: f32) {
+        match self
+            .rate_changes
+            .binary_search_by_key(&since, |(date, _)| *date)
+        {
+            Ok(i) => self.rate_changes[i].1 = rate,
+            Err(i) => self.rate_changes.insert(i, (since, rate)),
+        }
+    }
+
+    /// Cost of the given work sessions, if the project has an hourly rate or fixed fees.
+    ///
+    /// Every billable work session is billed at the rate of its subproject, falling back to the
+    /// rate of the closest parent subproject with one and then to the project's rate in effect on
+    /// the day it started. Its fixed fee comes on top. Time is summed up per rate and only
+    /// converted to hours and money at the very end.
+    fn cost_of<'a, I: IntoIterator<Item = &'a WorkSession>>(
+        &self,
+        work_sessions: I,
+        now: DateTime<Local>,
+    ) -> Option<f32> {
+        if !self.has_costs() {
+            return None;
+        }
+        let mut rate_durations: BTreeMap<(usize, Option<usize>), chrono::Duration> =
+            BTreeMap::new();
+        let mut fees = 0f32;
+        for work_session in work_sessions.into_iter().filter(|w| w.billable) {
+            fees += work_session.fixed_fee.unwrap_or(0f32);
+            let subproject = work_session
+                .subproject
+                .and_then(|id| self.rate_subproject(id))
+                .map(|s| s.id);
+            let period = match subproject {
+                Some(_) => 0,
+                None => self.rate_period(work_session.start.naive_local().date()),
+            };
+            let total = rate_durations
+                .entry((period, subproject))
+                .or_insert_with(chrono::Duration::zero);
+            *total = *total + self.billed_duration(work_session, now);
+        }
+        Some(
+            rate_durations
+                .into_iter()
+                .fold(fees, |total, ((period, subproject), duration)| {
+                    let rate = match subproject.and_then(|id| self.rate_subproject(id)) {
+                        Some(s) => s.hourly_rate,
+                        None => self.period_rate(period),
+                    };
+                    total + hours(duration) * rate.unwrap_or(0f32)
+                }),
+        )
+    }
+
+    /// Every billable work session with its tracked and its billed duration.
+    fn reconciled_durations(
+        &self,
+        now: DateTime<Local>,
+    ) -> Vec<(&WorkSession, chrono::Duration, chrono::Duration)> {
+        self.work_sessions
+            .iter()
+            .filter(|work_session| work_session.billable)
+            .map(|work_session| {
+                (
+                    work_session,
+                    work_session.duration(now),
+                    self.billed_duration(work_session, now),
+                )
+            })
+            .collect()
+    }
+
+    /// Duration of a work session rounded to the billing increment, if there is one.
+    fn billed_duration(
+        &self,
+        work_session: &WorkSession,
+        now: DateTime<Local>,
+    ) -> chrono::Duration {
+        match self.billing_increment_minutes {
+            Some(increment) => {
+                round_duration(work_session.duration(now), increment, self.rounding_mode())
             }
-            None => 112,
-        };
-        let split_description =
-            split_description_string(&work_session.description, (width - 72).into());
-        let stop_time = match work_session.stop {
-            Some(s) => s,
-            None => Local::now(),
-        };
-        let duration = (stop_time - work_session.start).num_minutes() as f32 / 60f32;
-        work_time += duration;
-        let homeoffice_mark;
-        if work_session.homeoffice {
-            homeoffice_mark = "✔";
-        } else {
-            homeoffice_mark = "";
+            None => work_session.duration(now),
         }
-        match time_sheet.hourly_rate {
-            Some(r) => {
-                let session_cost = duration * r;
-                table.add_row(row![
-                    r->i,
-                    work_session.start.format(DATETIME_FORMAT),
-                    stop_time.format(DATETIME_FORMAT),
-                    homeoffice_mark,
-                    r->format!("{:.02}", duration),
-                    r->format!("{:.02}", session_cost),
-                    split_description
-                ]);
-                project_cost += session_cost;
+    }
+
+    fn work_session_cost(&self, work_session: &WorkSession, now: DateTime<Local>) -> Option<f32> {
+        self.cost_of(std::iter::once(work_session), now)
+    }
+
+    /// Cost of all work sessions grouped by the key computed for each of them.
+    fn costs_by<K: Ord, F: Fn(&WorkSession) -> K>(
+        &self,
+        now: DateTime<Local>,
+        key: F,
+    ) -> BTreeMap<K, f32> {
+        let mut groups: BTreeMap<K, Vec<&WorkSession>> = BTreeMap::new();
+        for work_session in &self.work_sessions {
+            groups
+                .entry(key(work_session))
+                .or_default()
+                .push(work_session);
+        }
+        groups
+            .into_iter()
+            .filter_map(|(k, work_sessions)| Some((k, self.cost_of(work_sessions, now)?)))
+            .collect()
+    }
+
+    /// Total cost of all work sessions, if the project has an hourly rate.
+    pub fn total_cost(&self) -> Option<f32> {
+        self.cost_of(&self.work_sessions, Local::now())
+    }
+
+    /// Days spent in homeoffice grouped by year.
+    ///
+    /// A day counts as homeoffice day if more than `homeoffice_day_threshold`, by default half,
+    /// of the time worked on it was spent in homeoffice. A work session spanning midnight counts
+    /// for every calendar day it touches with the time spent on that day. Every year with any
+    /// work session is present, even without homeoffice days.
+    fn homeoffice_days(&self, now: DateTime<Local>) -> BTreeMap<String, BTreeSet<Date<Local>>> {
+        let mut days: BTreeMap<Date<Local>, (chrono::Duration, chrono::Duration)> = BTreeMap::new();
+        for work_session in &self.work_sessions {
+            for work_date in work_session.dates(now) {
+                let duration = work_session.duration_on(work_date, now);
+                let (homeoffice, total) = days
+                    .entry(work_date)
+                    .or_insert((chrono::Duration::zero(), chrono::Duration::zero()));
+                if work_session.homeoffice {
+                    *homeoffice = *homeoffice + duration;
+                }
+                *total = *total + duration;
             }
-            None => {
-                table.add_row(row![
-                    r->i,
-                    work_session.start.format(DATETIME_FORMAT),
-                    stop_time.format(DATETIME_FORMAT),
-                    homeoffice_mark,
-                    r->format!("{:.02}h", duration),
-                    split_description
-                ]);
+        }
+        let threshold = self
+            .homeoffice_day_threshold
+            .unwrap_or(DEFAULT_HOMEOFFICE_DAY_THRESHOLD);
+        let mut homeoffice_map: BTreeMap<String, BTreeSet<Date<Local>>> = BTreeMap::new();
+        for (date, (homeoffice, total)) in days {
+            let year_dates = homeoffice_map
+                .entry(format!("{}", date.format("%Y")))
+                .or_default();
+            if homeoffice.num_seconds() as f32 > threshold * total.num_seconds() as f32 {
+                year_dates.insert(date);
             }
+        }
+        homeoffice_map
+    }
+
+    /// Statistics over the durations of all work sessions, `None` without any work session.
+    fn session_stats(&self, now: DateTime<Local>) -> Option<SessionStats> {
+        let mut durations: Vec<(chrono::Duration, DateTime<Local>)> = self
+            .work_sessions
+            .iter()
+            .map(|work_session| (work_session.duration(now), work_session.start))
+            .collect();
+        durations.sort();
+        let count = durations.len() as i32;
+        let &shortest = durations.first()?;
+        let &longest = durations.last()?;
+        let total = durations
+            .iter()
+            .fold(chrono::Duration::zero(), |total, (d, _)| total + *d);
+        let middle = durations.len() / 2;
+        let median = if durations.len() % 2 == 0 {
+            (durations[middle - 1].0 + durations[middle].0) / 2
+        } else {
+            durations[middle].0
         };
+        Some(SessionStats {
+            mean: total / count,
+            median,
+            longest,
+            shortest,
+        })
+    }
 
-        let work_date = work_session.start.date();
-        let year = format!("{}", work_date.format("%Y"));
+    /// Number of distinct calendar days any work session started on.
+    fn days_worked(&self) -> usize {
+        self.work_sessions
+            .iter()
+            .map(|work_session| work_session.start.date())
+            .collect::<BTreeSet<_>>()
+            .len()
+    }
 
-        if homeoffice_map.get(&year).is_none() {
-            homeoffice_map.insert(year.clone(), Vec::new());
+    /// Time tracked per calendar day, splitting work sessions spanning midnight.
+    fn daily_durations(&self, now: DateTime<Local>) -> BTreeMap<NaiveDate, chrono::Duration> {
+        let mut days: BTreeMap<NaiveDate, chrono::Duration> = BTreeMap::new();
+        for work_session in &self.work_sessions {
+            for work_date in work_session.dates(now) {
+                let total = days
+                    .entry(work_date.naive_local())
+                    .or_insert_with(chrono::Duration::zero);
+                *total = *total + work_session.duration_on(work_date, now);
+            }
         }
+        days
+    }
 
-        let mut homeoffice_vec = homeoffice_map.get(&year).unwrap().clone();
+    /// Time tracked per year, attributed to the year each work session started in.
+    fn yearly_durations(&self, now: DateTime<Local>) -> BTreeMap<String, chrono::Duration> {
+        let mut yearly_map: BTreeMap<String, chrono::Duration> = BTreeMap::new();
+        for work_session in &self.work_sessions {
+            let year = format!("{}", work_session.start.format("%Y"));
+            let total = yearly_map
+                .entry(year)
+                .or_insert_with(chrono::Duration::zero);
+            *total = *total + work_session.duration(now);
+        }
+        yearly_map
+    }
 
-        if !homeoffice_vec.contains(&work_date) && work_session.homeoffice {
-            homeoffice_vec.push(work_date);
-            homeoffice_map.insert(year, homeoffice_vec);
+    /// Time spent in homeoffice per year, attributed to the year each work session started in.
+    fn homeoffice_durations(&self, now: DateTime<Local>) -> BTreeMap<String, chrono::Duration> {
+        let mut yearly_map: BTreeMap<String, chrono::Duration> = BTreeMap::new();
+        for work_session in self.work_sessions.iter().filter(|w| w.homeoffice) {
+            let year = format!("{}", work_session.start.format("%Y"));
+            let total = yearly_map
+                .entry(year)
+                .or_insert_with(chrono::Duration::zero);
+            *total = *total + work_session.duration(now);
         }
+        yearly_map
     }
 
-    table.printstd();
+    /// Time tracked per day of the week, indexed from Monday (0) to Sunday (6).
+    fn weekday_durations(&self, now: DateTime<Local>) -> [chrono::Duration; 7] {
+        let mut weekdays = [chrono::Duration::zero(); 7];
+        for work_session in &self.work_sessions {
+            let day = work_session.start.weekday().num_days_from_monday() as usize;
+            weekdays[day] = weekdays[day] + work_session.duration(now);
+        }
+        weekdays
+    }
 
-    println!();
+    /// Time worked per hour of the day, splitting work sessions at full hours.
+    fn hour_durations(&self, now: DateTime<Local>) -> [chrono::Duration; 24] {
+        let mut hours = [chrono::Duration::zero(); 24];
+        for work_session in &self.work_sessions {
+            let stop = work_session.stop.unwrap_or(now);
+            let mut from = work_session.start;
+            while from < stop {
+                let full_hour = from
+                    .with_minute(0)
+                    .and_then(|t| t.with_second(0))
+                    .and_then(|t| t.with_nanosecond(0))
+                    .unwrap_or(from);
+                let to = (full_hour + chrono::Duration::hours(1)).min(stop);
+                hours[from.hour() as usize] = hours[from.hour() as usize] + (to - from);
+                from = to;
+            }
+        }
+        hours
+    }
 
-    let mut homeoffice_table = Table::new();
-    homeoffice_table.set_format(*format::consts::FORMAT_NO_BORDER_LINE_SEPARATOR);
-    homeoffice_table.set_titles(row!["year", "days in homeoffice"]);
-    let mut year_vec = homeoffice_map.keys().collect::<Vec<&String>>();
-    year_vec.sort();
-    for year in year_vec {
-        homeoffice_table.add_row(row![
-            year,
-            homeoffice_map.get(year).unwrap_or(&Vec::new()).len()
-        ]);
+    /// IDs of work sessions starting after `now`, most likely caused by a typo or clock skew.
+    fn future_work_sessions(&self, now: DateTime<Local>) -> Vec<usize> {
+        self.work_sessions
+            .iter()
+            .filter(|work_session| work_session.start > now)
+            .map(|work_session| work_session.id)
+            .collect()
     }
-    homeoffice_table.printstd();
 
-    println!();
+    /// Untracked intervals between consecutive work sessions on the same day.
+    fn gaps(&self) -> Vec<(DateTime<Local>, DateTime<Local>)> {
+        self.work_sessions
+            .windows(2)
+            .filter_map(|pair| match pair[0].stop {
+                Some(stop) if stop < pair[1].start && stop.date() == pair[1].start.date() => {
+                    Some((stop, pair[1].start))
+                }
+                _ => None,
+            })
+            .collect()
+    }
 
-    let mut total_table = Table::new();
-    total_table.add_row(row!["Total work time", r->format!("{:.02}h", work_time)]);
-    if time_sheet.hourly_rate.is_some() {
-        total_table.add_row(row!["Total project cost", r->format!("{:.02}€", project_cost)]);
+    /// Days working longer than `max_daily_hours` and too short rests between working days.
+    ///
+    /// Work sessions count for the day they started on. Every violation comes with the date, a
+    /// description, the offending duration and the limit it breaks.
+    fn legal_violations(
+        &self,
+        now: DateTime<Local>,
+    ) -> Vec<(
+        Date<Local>,
+        &'static str,
+        chrono::Duration,
+        chrono::Duration,
+    )> {
+        let limit = |hours: f32| chrono::Duration::minutes((hours * 60.).round() as i64);
+        let max_daily = limit(self.max_daily_hours.unwrap_or(DEFAULT_MAX_DAILY_HOURS));
+        let min_rest = limit(self.min_rest_hours.unwrap_or(DEFAULT_MIN_REST_HOURS));
+
+        let mut days: BTreeMap<Date<Local>, (chrono::Duration, DateTime<Local>, DateTime<Local>)> =
+            BTreeMap::new();
+        for work_session in &self.work_sessions {
+            let stop = work_session.stop.unwrap_or(now);
+            let day = days.entry(work_session.start.date()).or_insert((
+                chrono::Duration::zero(),
+                work_session.start,
+                stop,
+            ));
+            day.0 = day.0 + work_session.duration(now);
+            day.1 = day.1.min(work_session.start);
+            day.2 = day.2.max(stop);
+        }
+
+        let mut violations = Vec::new();
+        let mut previous_stop: Option<DateTime<Local>> = None;
+        for (date, (duration, first_start, last_stop)) in days {
+            if let Some(stop) = previous_stop {
+                let rest = first_start - stop;
+                if rest < min_rest {
+                    violations.push((date, "Rest before the day too short", rest, min_rest));
+                }
+            }
+            if duration > max_daily {
+                violations.push((date, "Daily working time too long", duration, max_daily));
+            }
+            previous_stop = Some(last_stop);
+        }
+        violations
     }
-    total_table.printstd();
-    Ok(())
-}
 
-pub fn add_work_session_to_time_sheet(
-    _project: Option<&str>,
-    start: &str,
-    stop: Option<&str>,
-    description: Option<&str>,
-    homeoffice: bool,
-) -> Result<(), TimetrackerError> {
-    let work_session = WorkSession::new(
-        Local.datetime_from_str(start, DATETIME_FORMAT)?,
-        match stop {
-            Some(s) => Some(Local.datetime_from_str(s, DATETIME_FORMAT)?),
-            None => None,
-        },
-        match description {
-            Some(d) => String::from(d),
-            None => String::from(""),
-        },
-        homeoffice,
-    );
+    /// Working hours available between `from` and `to`, both inclusive.
+    ///
+    /// The first `working_days_per_week` days of every week, starting on Monday, are working
+    /// days unless they are listed as holidays.
+    fn available_hours(&self, from: NaiveDate, to: NaiveDate) -> f32 {
+        let working_days_per_week = self
+            .working_days_per_week
+            .unwrap_or(DEFAULT_WORKING_DAYS_PER_WEEK);
+        let mut working_days = 0;
+        let mut date = from;
+        while date <= to {
+            if date.weekday().num_days_from_monday() < working_days_per_week.into()
+                && !self.holidays.contains(&date)
+            {
+                working_days += 1;
+            }
+            date = date.succ();
+        }
+        working_days as f32 * HOURS_PER_WORKING_DAY
+    }
 
-    let time_sheet_path = Path::new("time_sheet.json");
-    let mut time_sheet = TimeSheet::load(&time_sheet_path)?;
-    time_sheet.work_sessions.push(work_session);
-    time_sheet.work_sessions.sort();
-    time_sheet.save(&time_sheet_path)?;
-    Ok(())
-}
+    /// Insert a work session at its chronological position.
+    ///
+    /// Fails without changing the time sheet if the new session overlaps an existing one.
+    pub fn add_work_session(
+        &mut self,
+        mut work_session: WorkSession,
+    ) -> Result<(), TimetrackerError> {
+        let position = self
+            .work_sessions
+            .binary_search(&work_session)
+            .unwrap_or_else(|p| p);
+        work_session.id = self.next_session_id;
+        self.work_sessions.insert(position, work_session);
+        if let Err(e) = self.check_overlaps() {
+            self.work_sessions.remove(position);
+            return Err(e);
+        }
+        self.next_session_id += 1;
+        Ok(())
+    }
 
-pub fn add_subproject(name: &str, description: &str) -> Result<(), TimetrackerError> {
-    //! Add a new subproject to the time sheet
+    /// Time the running work session should have been stopped at, if it has been going on for
+    /// longer than `auto_stop_after_hours`.
+    fn auto_stop_time(&self, now: DateTime<Local>) -> Option<DateTime<Local>> {
+        let hours = self.auto_stop_after_hours?;
+        let work_session = self.work_sessions.last().filter(|w| w.stop.is_none())?;
+        let cutoff = work_session.start + chrono::Duration::seconds((hours * 3600.).round() as i64);
+        if now > cutoff {
+            Some(cutoff)
+        } else {
+            None
+        }
+    }
 
-    println!("{} | {}", name, description);
-    let time_sheet_path = Path::new("time_sheet.json");
-    let mut time_sheet = TimeSheet::load(&time_sheet_path)?;
-    let subproject = SubProject::new(
-        time_sheet.subprojects.len(),
-        name.to_string(),
-        description.to_string(),
-    );
-    time_sheet.subprojects.push(subproject);
-    time_sheet.save(&time_sheet_path)?;
-    Ok(())
-}
+    /// Hand out the ID for a new work session.
+    fn new_session_id(&mut self) -> usize {
+        let id = self.next_session_id;
+        self.next_session_id += 1;
+        id
+    }
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use proptest::prelude::*;
+    /// Current position of the work session with the given ID.
+    fn work_session_position(&self, id: usize) -> Result<usize, TimetrackerError> {
+        self.work_sessions
+            .iter()
+            .position(|work_session| work_session.id == id)
+            .ok_or_else(|| TimetrackerError::TimeSheet(format!("No work session with ID {}!", id)))
+    }
 
-    /*
-    prop_compose! {
-        fn compose_work_sessions(max_length: usize)(work_sessions in any_with::<Vec<WorkSession>>(proptest::collection::size_range(max_length).lift())) -> Vec<WorkSession> {
-            work_sessions
+    /// Merge the work sessions, subprojects and activities of another time sheet into this one.
+    ///
+    /// Work sessions that are already present are skipped. Work sessions overlapping with existing
+    /// ones are skipped as well and reported as warnings. Subprojects and activities are matched
+    /// by name, and the subprojects and activities of merged work sessions as well as the parents
+    /// of new subprojects are mapped to the IDs of this sheet.
+    fn merge(&mut self, other: TimeSheet) -> MergeSummary {
+        let mut summary = MergeSummary::default();
+        let mut subproject_ids = BTreeMap::new();
+        let mut parents = Vec::new();
+        for subproject in other.subprojects {
+            let id = match self.subprojects.iter().find(|s| s.name == subproject.name) {
+                Some(s) => s.id,
+                None => {
+                    let id = self.subprojects.iter().map(|s| s.id + 1).max().unwrap_or(0);
+                    let mut new = SubProject::new(id, subproject.name, subproject.description);
+                    new.hourly_rate = subproject.hourly_rate;
+                    if let Some(parent) = subproject.parent {
+                        parents.push((id, parent));
+                    }
+                    self.subprojects.push(new);
+                    id
+                }
+            };
+            subproject_ids.insert(subproject.id, id);
+        }
+        // Parents can be listed after their children, so they are mapped once all IDs are known
+        for (id, parent) in parents {
+            if let Some(s) = self.subprojects.iter_mut().find(|s| s.id == id) {
+                s.parent = subproject_ids.get(&parent).copied();
+            }
+        }
+        let mut activity_ids = BTreeMap::new();
+        for activity in other.activities {
+            let id = match self.activities.iter().find(|a| a.name == activity.name) {
+                Some(a) => a.id,
+                None => {
+                    let id = self.activities.iter().map(|a| a.id + 1).max().unwrap_or(0);
+                    self.activities
+                        .push(Activity::new(id, activity.name, activity.description));
+                    id
+                }
+            };
+            activity_ids.insert(activity.id, id);
         }
+        for mut work_session in other.work_sessions {
+            if self.work_sessions.contains(&work_session) {
+                summary.duplicates += 1;
+                continue;
+            }
+            work_session.activity = work_session
+                .activity
+                .and_then(|a| activity_ids.get(&a).copied());
+            work_session.subproject = work_session
+                .subproject
+                .and_then(|s| subproject_ids.get(&s).copied());
+            match self.add_work_session(work_session) {
+                Ok(()) => summary.added += 1,
+                Err(e) => summary.warnings.push(e.to_string()),
+            }
+        }
+        summary
     }
-    */
 
-    proptest! {
-        #[test]
-        fn test_subproject_creation(id: usize, name in "\\PC*", description in "\\PC*") {
-            let subproject = SubProject::new(id, name.clone(), description.clone());
-            prop_assert_eq!(subproject.id, id);
-            prop_assert_eq!(subproject.name, name);
-            prop_assert_eq!(subproject.description, description);
+    fn get_work_session_mut(&mut self, id: usize) -> Result<&mut WorkSession, TimetrackerError> {
+        self.work_sessions
+            .iter_mut()
+            .find(|work_session| work_session.id == id)
+            .ok_or_else(|| TimetrackerError::TimeSheet(format!("No work session with ID {}!", id)))
+    }
+
+    /// Pairs of the ID of a duplicated work session and the ID of the earlier one it duplicates.
+    ///
+    /// Work sessions count as duplicates if they are equal, i.e. have the same start, stop and
+    /// description. Sessions differing only in their homeoffice flag or other details are
+    /// duplicates as well.
+    fn duplicate_work_sessions(&self) -> Vec<(usize, usize)> {
+        self.work_sessions
+            .iter()
+            .enumerate()
+            .filter_map(|(i, work_session)| {
+                self.work_sessions[..i]
+                    .iter()
+                    .find(|earlier| *earlier == work_session)
+                    .map(|earlier| (work_session.id, earlier.id))
+            })
+            .collect()
+    }
+
+    /// Close a still running work session at `stop`.
+    ///
+    /// The stop has to be after the start of the work session and must not reach into the
+    /// following one.
+    fn finalize_work_session(
+        &mut self,
+        id: usize,
+        stop: DateTime<Local>,
+    ) -> Result<(), TimetrackerError> {
+        let position = self.work_session_position(id)?;
+        let work_session = &self.work_sessions[position];
+        if work_session.stop.is_some() {
+            return Err(TimetrackerError::TimeSheet(format!(
+                "Work session {} is already stopped!",
+                id
+            )));
         }
+        if stop <= work_session.start {
+            return Err(TimetrackerError::TimeSheet(format!(
+                "Stop time {} is not after the start of work session {} at {}!",
+                stop.format(self.datetime_format()),
+                id,
+                work_session.start.format(self.datetime_format())
+            )));
+        }
+        if let Some(next) = self.work_sessions.get(position + 1) {
+            if stop > next.start {
+                return Err(TimetrackerError::TimeSheet(format!(
+                    "Stop time {} overlaps with work session {} starting at {}!",
+                    stop.format(self.datetime_format()),
+                    next.id,
+                    next.start.format(self.datetime_format())
+                )));
+            }
+        }
+        self.work_sessions[position].stop = Some(stop);
+        Ok(())
+    }
 
-        #[test]
-        fn test_subproject_cmp(id_1: usize, id_2: usize, name_1 in "\\PC*", name_2 in "\\PC*", description_1 in "\\PC*", description_2 in "\\PC*") {
-            let subproject_1 = SubProject::new(id_1, name_1, description_1);
-            let subproject_2 = SubProject::new(id_2, name_2, description_2);
-            prop_assert!((id_1 < id_2) == (subproject_1 < subproject_2));
-            prop_assert!((id_1 > id_2) == (subproject_1 > subproject_2));
-            prop_assert!((id_1 == id_2) == (subproject_1 == subproject_2));
+    /// Make sure no two work sessions overlap. Expects the work sessions to be sorted.
+    fn check_overlaps(&self) -> Result<(), TimetrackerError> {
+        for pair in self.work_sessions.windows(2) {
+            match pair[0].stop {
+                Some(stop) if stop <= pair[1].start => (),
+                _ => {
+                    return Err(TimetrackerError::TimeSheet(format!(
+                        "Work session starting at {} overlaps with work session starting at {}!",
+                        pair[0].start.format(self.datetime_format()),
+                        pair[1].start.format(self.datetime_format())
+                    )));
+                }
+            }
         }
+        Ok(())
+    }
+}
 
-        #[test]
-        fn test_time_sheet_creation(project_name in "\\PC*", hourly_rate: f32) {
-            let time_sheet = TimeSheet::new(project_name.clone(), Some(hourly_rate));
-            prop_assert_eq!(time_sheet.project_name, project_name);
-            prop_assert_eq!(time_sheet.hourly_rate, Some(hourly_rate));
-            assert_eq!(time_sheet.work_sessions.len(), 0);
+impl TimeSheet {
+    fn to_json(&self) -> serde_json::Result<String> {
+        serde_json::to_string(&self)
+    }
+
+    /// TOML needs all plain values of a table before its subtables, which only the generic
+    /// `toml::Value` takes care of.
+    fn to_toml(&self) -> Result<String, toml::ser::Error> {
+        toml::to_string_pretty(&toml::Value::try_from(self)?)
+    }
+
+    /// Save the time sheet as JSON, or TOML if the extension is `.toml`, replacing the given file.
+    pub fn save(&self, path: &Path) -> Result<(), TimetrackerError> {
+        verbose!(
+            "Saving {} work sessions to {} as {:?}",
+            self.work_sessions.len(),
+            path.display(),
+            StorageFormat::of(path)
+        );
+        let content = match StorageFormat::of(path) {
+            StorageFormat::Json => self.to_json()?,
+            StorageFormat::Toml => self.to_toml()?,
+        };
+        write_atomically(path, content.as_bytes())
+    }
+}
+
+/// Replace the file at `path` with `content`.
+///
+/// The content goes to a temporary file next to `path` first, which is then moved into place, so
+/// a failed write never leaves a half written file behind.
+fn write_atomically(path: &Path, content: &[u8]) -> Result<(), TimetrackerError> {
+    let mut temp_name = std::ffi::OsString::from(".");
+    temp_name.push(path.file_name().unwrap_or_default());
+    temp_name.push(".tmp");
+    let temp_path = path.with_file_name(temp_name);
+    verbose!("Writing {} bytes to {}", content.len(), temp_path.display());
+    {
+        let file = std::fs::File::create(&temp_path)?;
+        let mut writer = BufWriter::new(&file);
+        writer.write_all(content)?;
+        writer.flush()?;
+    }
+    verbose!("Moving {} to {}", temp_path.display(), path.display());
+    std::fs::rename(&temp_path, path)?;
+    Ok(())
+}
+
+/// Write an export to `path`, or to stdout if no path is given.
+fn write_export(content: &[u8], path: Option<&Path>) -> Result<(), TimetrackerError> {
+    match path {
+        Some(p) => write_atomically(p, content),
+        None => {
+            std::io::stdout().write_all(content)?;
+            Ok(())
+        }
+    }
+}
+
+/// Width to wrap descriptions at, either as given or derived from the terminal width.
+fn description_width(width: Option<usize>) -> usize {
+    match width {
+        Some(w) => w,
+        None => match terminal_size() {
+            Some((Width(w), Height(_))) => usize::from(w)
+                .saturating_sub(FIXED_COLUMNS_WIDTH)
+                .max(MIN_DESCRIPTION_WIDTH),
+            None => DEFAULT_DESCRIPTION_WIDTH,
+        },
+    }
+}
+
+/// Wrap a description at `max_line_length` graphemes.
+///
+/// Lines are broken between words where possible, words longer than a whole line are broken
+/// apart. A line is never longer than `max_line_length`, unless that is zero.
+fn split_description_string(desc_string: &str, max_line_length: usize) -> String {
+    let max_line_length = max_line_length.max(1);
+    let mut lines_vec = vec![];
+    let mut line = String::new();
+    let mut line_length = 0;
+    for word in desc_string.split(' ') {
+        let graphemes = word.graphemes(true).collect::<Vec<&str>>();
+        let separator_length = if line_length > 0 { 1 } else { 0 };
+        if line_length + separator_length + graphemes.len() <= max_line_length {
+            if separator_length > 0 {
+                line.push(' ');
+            }
+            line.push_str(word);
+            line_length += separator_length + graphemes.len();
+            continue;
+        }
+        if line_length > 0 {
+            lines_vec.push(line);
+            line = String::new();
+            line_length = 0;
+        }
+        let mut chunks = graphemes.chunks(max_line_length).peekable();
+        while let Some(chunk) = chunks.next() {
+            if chunks.peek().is_some() {
+                lines_vec.push(chunk.concat());
+            } else {
+                line = chunk.concat();
+                line_length = chunk.len();
+            }
+        }
+    }
+    lines_vec.push(line);
+    lines_vec.join("\n")
+}
+
+/// Environment variable naming the time sheet to use when no `--file` is given
+pub const TIME_SHEET_ENV_VAR: &str = "TIMETRACKER_FILE";
+
+/// Path of the time sheet when neither `--file` nor `TIMETRACKER_FILE` is given
+const DEFAULT_TIME_SHEET_PATH: &str = "time_sheet.json";
+
+/// Resolve the path of the time sheet: an explicitly given file wins over `env_file`, the value
+/// of the `TIMETRACKER_FILE` environment variable, which wins over `time_sheet.json`.
+pub fn time_sheet_path(file: Option<&str>, env_file: Option<&std::ffi::OsStr>) -> PathBuf {
+    let path = match file {
+        Some(f) => {
+            verbose!("Using the time sheet given by --file");
+            PathBuf::from(f)
+        }
+        None => match env_file {
+            Some(p) if !p.is_empty() => {
+                verbose!("Using the time sheet given by ${}", TIME_SHEET_ENV_VAR);
+                PathBuf::from(p)
+            }
+            _ => {
+                verbose!("Using the default time sheet");
+                PathBuf::from(DEFAULT_TIME_SHEET_PATH)
+            }
+        },
+    };
+    match env::current_dir() {
+        Ok(dir) => verbose!("Time sheet path resolved to {}", dir.join(&path).display()),
+        Err(e) => verbose!(
+            "Time sheet path is {}, current directory unknown: {}",
+            path.display(),
+            e
+        ),
+    }
+    path
+}
+
+/// Environment variable overriding the location of the global configuration file
+pub const CONFIG_ENV_VAR: &str = "TIMETRACKER_CONFIG";
+
+/// Defaults for all time sheets, read from `timetracker/config.toml` in the user's configuration
+/// directory, e.g. `~/.config/timetracker/config.toml`.
+///
+/// Settings of a time sheet take precedence over these defaults, command line options take
+/// precedence over both.
+#[derive(Deserialize, Default, Clone, Debug, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct GlobalConfig {
+    /// Hourly rate of new projects initialized without one
+    pub hourly_rate: Option<f32>,
+    /// Currency to show amounts of money in
+    pub currency: Option<String>,
+    /// How to format costs and hours
+    pub locale: Option<Locale>,
+    /// Whether new work sessions are done in homeoffice unless stated otherwise
+    pub default_homeoffice: Option<bool>,
+    /// How work sessions are rounded to the billing increment
+    pub rounding_mode: Option<RoundingMode>,
+    /// Track start and stop times to the second
+    pub seconds_precision: Option<bool>,
+    /// `strftime` format to show start and stop times in, e.g. `%d.%m.%Y %H:%M`
+    pub datetime_format: Option<String>,
+}
+
+thread_local! {
+    /// Global configuration applied to every time sheet loaded, see `set_global_config`
+    static GLOBAL_CONFIG: RefCell<GlobalConfig> = RefCell::new(GlobalConfig::default());
+}
+
+/// Use `config` as defaults for all time sheets loaded or initialized from now on.
+///
+/// Without calling this, e.g. in tests, no global defaults apply at all.
+pub fn set_global_config(config: GlobalConfig) {
+    GLOBAL_CONFIG.with(|global| *global.borrow_mut() = config);
+}
+
+fn global_config() -> GlobalConfig {
+    GLOBAL_CONFIG.with(|global| global.borrow().clone())
+}
+
+impl GlobalConfig {
+    /// Location of the global configuration file, `None` if there is no configuration directory.
+    pub fn path() -> Option<PathBuf> {
+        match env::var_os(CONFIG_ENV_VAR) {
+            Some(p) if !p.is_empty() => Some(PathBuf::from(p)),
+            _ => dirs::config_dir().map(|dir| dir.join("timetracker").join("config.toml")),
+        }
+    }
+
+    /// Read the global configuration, falling back to no defaults at all if there is none.
+    pub fn load() -> Result<GlobalConfig, TimetrackerError> {
+        let path = match GlobalConfig::path() {
+            Some(p) if p.exists() => p,
+            _ => {
+                verbose!("No global configuration found");
+                return Ok(GlobalConfig::default());
+            }
+        };
+        verbose!("Loading global configuration from {}", path.display());
+        let config: GlobalConfig = toml::from_str(&std::fs::read_to_string(&path)?)?;
+        if let Some(format) = &config.datetime_format {
+            check_datetime_format(format)?;
+        }
+        Ok(config)
+    }
+}
+
+/// Make sure chrono understands every specifier of a format, as formatting panics otherwise.
+fn check_datetime_format(format: &str) -> Result<(), TimetrackerError> {
+    if chrono::format::StrftimeItems::new(format).any(|item| item == chrono::format::Item::Error) {
+        return Err(TimetrackerError::TimeSheet(format!(
+            "Invalid datetime format \"{}\"!",
+            format
+        )));
+    }
+    Ok(())
+}
+
+/// Make sure an hourly rate is a finite, non-negative number.
+fn check_rate(hourly_rate: f32) -> Result<(), TimetrackerError> {
+    if hourly_rate.is_finite() && hourly_rate >= 0f32 {
+        Ok(())
+    } else {
+        Err(TimetrackerError::TimeSheet(format!(
+            "Invalid hourly rate {}! It must be a non-negative number.",
+            hourly_rate
+        )))
+    }
+}
+
+pub fn initialize_project(
+    name: String,
+    hourly_rate: Option<f32>,
+    force: bool,
+    assume_yes: bool,
+    path: &Path,
+) -> Result<(), TimetrackerError> {
+    let hourly_rate = hourly_rate.or(global_config().hourly_rate);
+    if let Some(r) = hourly_rate {
+        check_rate(r)?;
+    }
+    if path.exists() && !force {
+        return Err(TimetrackerError::TimeSheet(format!(
+            "A time sheet already exists at {}. Use --force to overwrite it.",
+            path.display()
+        )));
+    }
+    if path.exists() {
+        confirm(
+            &format!("Overwrite the existing time sheet at {}?", path.display()),
+            &mut std::io::stdin().lock(),
+            assume_yes,
+        )?;
+    }
+    println!(
+        "Initializing Project {} with an hourly rate of {:.02}€",
+        name,
+        hourly_rate.unwrap_or(0f32)
+    );
+    let time_sheet = TimeSheet::new(name, hourly_rate);
+    time_sheet.save(path)?;
+    Ok(())
+}
+
+/// Drop the seconds of a time, which `DATETIME_FORMAT` can't represent anyway.
+fn truncate_to_minute(time: DateTime<Local>) -> DateTime<Local> {
+    time.with_second(0).unwrap().with_nanosecond(0).unwrap()
+}
+
+/// Round a time to the nearest multiple of `grid` minutes since midnight, dropping seconds.
+fn snap_to_grid(time: DateTime<Local>, grid: u32) -> DateTime<Local> {
+    let minutes = i64::from(time.hour() * 60 + time.minute());
+    let grid = i64::from(grid.max(1));
+    let snapped = (minutes + grid / 2) / grid * grid;
+    truncate_to_minute(time) + chrono::Duration::minutes(snapped - minutes)
+}
+
+/// Parse an optional time override given as `DATETIME_FORMAT` or fall back to the current time,
+/// truncated to the minute or, with `seconds`, to the second.
+///
+/// Overrides further than `MAX_AT_OFFSET_HOURS` from now are most likely typos and rejected
+/// unless `force` is set.
+fn time_or_now(
+    at: Option<&str>,
+    force: bool,
+    seconds: bool,
+) -> Result<DateTime<Local>, TimetrackerError> {
+    let now = Local::now();
+    match at {
+        Some(a) => {
+            let time = parse_datetime(a)?;
+            if !force && (time - now).num_minutes().abs() > MAX_AT_OFFSET_HOURS * 60 {
+                return Err(TimetrackerError::TimeSheet(format!(
+                    "{} is more than {} hours away from now! Use --force if this is intended.",
+                    time.format(DATETIME_FORMAT),
+                    MAX_AT_OFFSET_HOURS
+                )));
+            }
+            Ok(time)
+        }
+        None if seconds => Ok(now.with_nanosecond(0).unwrap()),
+        None => Ok(truncate_to_minute(now)),
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+pub fn start_working_session(
+    description: Option<&str>,
+    homeoffice: Option<bool>,
+    at: Option<&str>,
+    activity: Option<usize>,
+    subproject: Option<usize>,
+    billable: bool,
+    force: bool,
+    time_sheet_path: &Path,
+) -> Result<(), TimetrackerError> {
+    let mut time_sheet = TimeSheet::load(time_sheet_path)?;
+    let start_time = time_or_now(at, force, time_sheet.seconds_precision())?;
+    if start_time > Local::now() {
+        return Err(TimetrackerError::TimeSheet(format!(
+            "Work session would start in the future at {}!",
+            start_time.format(time_sheet.datetime_format())
+        )));
+    }
+    let mut desc = String::new();
+    if let Some(a) = activity {
+        time_sheet.check_activity(a)?;
+    }
+    if let Some(s) = subproject {
+        time_sheet.check_subproject(s)?;
+    }
+    if let Some(s) = time_sheet.work_sessions.last() {
+        match s.stop {
+            None => {
+                return Err(TimetrackerError::TimeSheet(String::from(
+                    "Last work session not finished!",
+                )));
+            }
+            Some(stop) if start_time < stop => {
+                return Err(TimetrackerError::TimeSheet(String::from(
+                    "Start time lies before the end of the last work session!",
+                )));
+            }
+            Some(_) => (),
+        }
+    };
+    match description {
+        Some(d) => {
+            desc.push_str(d);
+            println!(
+                "Start working on {} at {}",
+                desc,
+                start_time.format(time_sheet.datetime_format())
+            );
+        }
+        None => println!(
+            "Start working at {}",
+            start_time.format(time_sheet.datetime_format())
+        ),
+    };
+    let mut work_session = WorkSession::start_new_work_session(
+        start_time,
+        desc,
+        homeoffice.unwrap_or(time_sheet.default_homeoffice()),
+    );
+    work_session.activity = activity;
+    work_session.subproject = subproject;
+    work_session.billable = billable;
+    work_session.id = time_sheet.new_session_id();
+    time_sheet.work_sessions.push(work_session);
+    time_sheet.save(time_sheet_path)?;
+    Ok(())
+}
+
+/// Continue the most recent work session by removing its stop time again
+pub fn resume_last_work_session(time_sheet_path: &Path) -> Result<(), TimetrackerError> {
+    let mut time_sheet = TimeSheet::load(time_sheet_path)?;
+    let work_session = match time_sheet.work_sessions.last_mut() {
+        Some(w) => w,
+        None => {
+            return Err(TimetrackerError::TimeSheet(String::from(
+                "No work session to resume!",
+            )));
+        }
+    };
+    let stop = match work_session.stop {
+        Some(s) => s,
+        None => {
+            return Err(TimetrackerError::TimeSheet(String::from(
+                "Last work session not finished!",
+            )));
+        }
+    };
+    work_session.stop = None;
+    let (id, start) = (work_session.id, work_session.start);
+    println!(
+        "Resume working on work session {} started at {}, stopped at {}",
+        id,
+        start.format(time_sheet.datetime_format()),
+        stop.format(time_sheet.datetime_format())
+    );
+    time_sheet.save(time_sheet_path)?;
+    Ok(())
+}
+
+/// Drop the currently open work session without recording it
+pub fn discard_work_session(time_sheet_path: &Path) -> Result<(), TimetrackerError> {
+    let mut time_sheet = TimeSheet::load(time_sheet_path)?;
+    match time_sheet.work_sessions.last() {
+        Some(s) if s.stop.is_none() => {}
+        _ => {
+            return Err(TimetrackerError::TimeSheet(String::from(
+                "No unfinished work session found to discard!",
+            )));
+        }
+    }
+    let work_session = time_sheet.work_sessions.pop().unwrap();
+    println!(
+        "Discarded work session started at {}",
+        work_session.start.format(time_sheet.datetime_format())
+    );
+    time_sheet.save(time_sheet_path)?;
+    Ok(())
+}
+
+pub fn stop_working_session(
+    description: Option<&str>,
+    homeoffice: Option<bool>,
+    at: Option<&str>,
+    activity: Option<usize>,
+    force: bool,
+    time_sheet_path: &Path,
+) -> Result<(), TimetrackerError> {
+    let mut time_sheet = TimeSheet::load(time_sheet_path)?;
+    let stop_time = time_or_now(at, force, time_sheet.seconds_precision())?;
+    let mut desc = String::new();
+    if let Some(a) = activity {
+        time_sheet.check_activity(a)?;
+    }
+    match time_sheet.work_sessions.last() {
+        Some(s) => match s.stop {
+            None if stop_time < s.start => {
+                return Err(TimetrackerError::TimeSheet(String::from(
+                    "Stop time lies before the start of the current work session!",
+                )));
+            }
+            None => {
+                if let Some(m) = time_sheet.minimum_session_minutes {
+                    let duration = stop_time - s.start;
+                    if duration < chrono::Duration::minutes(i64::from(m)) {
+                        return Err(TimetrackerError::TimeSheet(format!(
+                            "The work session would only last {} minutes, less than the minimum \
+                             of {} minutes! Use `remove` to delete it if it was started by accident.",
+                            duration.num_minutes(),
+                            m
+                        )));
+                    }
+                }
+            }
+            Some(_) => {
+                return Err(TimetrackerError::TimeSheet(String::from(
+                    "No unfinished work session found to stop!",
+                )));
+            }
+        },
+        None => {
+            return Err(TimetrackerError::TimeSheet(String::from(
+                "No unfinished work session found to stop!",
+            )));
+        }
+    }
+    match description {
+        Some(d) => {
+            desc.push_str(d);
+            println!(
+                "Stop working on {} at {}",
+                desc,
+                stop_time.format(time_sheet.datetime_format())
+            );
+        }
+        None => println!(
+            "Stop working at {}",
+            stop_time.format(time_sheet.datetime_format())
+        ),
+    }
+    //time_sheet.work_sessions.last().unwrap().stop = Some(stop_time);
+    let mut last_work_session = time_sheet.work_sessions.pop().unwrap();
+    last_work_session.stop = Some(stop_time);
+    if description.is_some() {
+        last_work_session.description = desc;
+    }
+    if let Some(h) = homeoffice {
+        last_work_session.homeoffice = h;
+    }
+    if activity.is_some() {
+        last_work_session.activity = activity;
+    }
+    time_sheet.work_sessions.push(last_work_session);
+    time_sheet.save(time_sheet_path)?;
+    Ok(())
+}
+
+/// Append a note to the description of the currently running work session.
+pub fn append_description(text: &str, time_sheet_path: &Path) -> Result<(), TimetrackerError> {
+    let mut time_sheet = TimeSheet::load(time_sheet_path)?;
+    let work_session = time_sheet
+        .work_sessions
+        .iter_mut()
+        .rev()
+        .find(|work_session| work_session.stop.is_none())
+        .ok_or_else(|| {
+            TimetrackerError::TimeSheet(String::from("No unfinished work session found!"))
+        })?;
+    if !work_session.description.is_empty() {
+        work_session.description.push_str("; ");
+    }
+    work_session.description.push_str(text);
+    println!("Description is now: {}", work_session.description);
+    time_sheet.save(time_sheet_path)?;
+    Ok(())
+}
+
+/// Replace the project notes with `text`, or add it as a new line with `append`.
+pub fn edit_notes(
+    text: &str,
+    append: bool,
+    time_sheet_path: &Path,
+) -> Result<(), TimetrackerError> {
+    let mut time_sheet = TimeSheet::load(time_sheet_path)?;
+    if append && !time_sheet.notes.is_empty() {
+        time_sheet.notes.push('\n');
+    } else {
+        time_sheet.notes.clear();
+    }
+    time_sheet.notes.push_str(text);
+    time_sheet.save(time_sheet_path)?;
+    Ok(())
+}
+
+/// Print the project notes
+pub fn print_notes(time_sheet_path: &Path) -> Result<(), TimetrackerError> {
+    let time_sheet = TimeSheet::load(time_sheet_path)?;
+    if time_sheet.notes.is_empty() {
+        println!("No notes for this project yet.");
+    } else {
+        println!("{}", time_sheet.notes);
+    }
+    Ok(())
+}
+
+/// Switch from one working session to the next.
+pub fn switch_working_sessions(
+    description: Option<&str>,
+    homeoffice: Option<bool>,
+    time_sheet_path: &Path,
+) -> Result<(), TimetrackerError> {
+    // The next work session is billed like the one it replaces
+    let billable = TimeSheet::load(time_sheet_path)?
+        .work_sessions
+        .last()
+        .map_or(true, |work_session| work_session.billable);
+    stop_working_session(description, homeoffice, None, None, false, time_sheet_path)?;
+    start_working_session(
+        None,
+        homeoffice,
+        None,
+        None,
+        None,
+        billable,
+        false,
+        time_sheet_path,
+    )
+}
+
+/// Summary of a single work session as emitted by `analyze --json`
+#[derive(Serialize, Debug)]
+struct WorkSessionSummary {
+    id: usize,
+    start: DateTime<Local>,
+    stop: Option<DateTime<Local>>,
+    duration: f32,
+    cost: Option<f32>,
+    description: String,
+    homeoffice: bool,
+    activity: Option<usize>,
+    billable: bool,
+    fixed_fee: Option<f32>,
+}
+
+/// Summary of a whole time sheet as emitted by `analyze --json`
+#[derive(Serialize, Debug)]
+struct TimeSheetSummary {
+    project_name: String,
+    hourly_rate: Option<f32>,
+    total_work_time: f32,
+    total_cost: Option<f32>,
+    days_worked: usize,
+    homeoffice_days: BTreeMap<String, usize>,
+    homeoffice_hours: BTreeMap<String, f32>,
+    work_sessions: Vec<WorkSessionSummary>,
+}
+
+/// Statistics over the durations of work sessions
+#[derive(Debug, PartialEq)]
+struct SessionStats {
+    mean: chrono::Duration,
+    median: chrono::Duration,
+    /// Duration and start of the longest work session
+    longest: (chrono::Duration, DateTime<Local>),
+    /// Duration and start of the shortest work session
+    shortest: (chrono::Duration, DateTime<Local>),
+}
+
+/// Conventions for formatting numbers in reports
+#[derive(Deserialize, Clone, Copy, Debug, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum Locale {
+    /// Decimal point without digit grouping, e.g. `1234.56`
+    En,
+    /// Decimal comma with dots grouping thousands, e.g. `1.234,56`
+    De,
+}
+
+/// Alternative reports of `analyze_work_sheet`, summing up time per category
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Breakdown {
+    /// Time per day of the week, Monday to Sunday
+    Weekday,
+    /// Histogram of the time per hour of the day
+    Hour,
+}
+
+/// Columns of the work session table
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Column {
+    Id,
+    Start,
+    Stop,
+    /// Whether the work session was done in homeoffice
+    Homeoffice,
+    /// Whether the work session is billable
+    Billable,
+    Time,
+    /// Fixed fee of the work session
+    Fee,
+    Cost,
+    Description,
+}
+
+/// Options controlling which work sessions `analyze_work_sheet` considers and how it prints them
+#[derive(Clone, Default, Debug)]
+pub struct AnalyzeOptions {
+    /// Print a JSON summary instead of tables
+    pub json: bool,
+    /// Print the work sessions as tab separated values instead of tables
+    pub tsv: bool,
+    /// Leave out work sessions that are still running
+    pub closed_only: bool,
+    /// Wrap descriptions at this width instead of deriving it from the terminal
+    pub description_width: Option<usize>,
+    /// Show durations as `H:MM` instead of decimal hours
+    pub hours_minutes: bool,
+    /// Show the durations in the totals table as `Xh Ym`
+    pub totals_hm: bool,
+    /// Print only the total work time and cost, separated by a tab
+    pub quiet: bool,
+    /// Only use ASCII characters and print tables without borders
+    pub plain: bool,
+    /// Show amounts of money in this currency instead of euros
+    pub currency: Option<String>,
+    /// Factor to convert euros into `currency`
+    pub exchange_rate: Option<f32>,
+    /// Report the gaps between work sessions on the same day instead of the usual tables
+    pub gaps: bool,
+    /// Report days breaking the daily working time or rest limits instead of the usual tables
+    pub legal_check: bool,
+    /// Compare tracked and billed time of every billable work session instead of the usual tables
+    pub reconcile: bool,
+    /// Gaps of at least this many minutes are highlighted
+    pub gap_threshold: Option<u32>,
+    /// Number of decimal places for hours and amounts of money
+    pub precision: Option<usize>,
+    /// Print the time per category instead of the usual tables
+    pub breakdown: Option<Breakdown>,
+    /// Leave out work sessions started before this time
+    pub since: Option<DateTime<Local>>,
+    /// Only include work sessions done in homeoffice (`true`) or in the office (`false`)
+    pub homeoffice: Option<bool>,
+    /// Leave out work sessions shorter than this many minutes, from the tables and the totals
+    pub min_duration: Option<u32>,
+    /// Round the project total to a multiple of this amount in the displayed currency
+    pub round_total: Option<f32>,
+    /// How to format costs and hours, `Locale::En` if not given
+    pub locale: Option<Locale>,
+    /// Stop a running work session exceeding the configured auto stop time at that time
+    pub auto_fix: bool,
+    /// Only include work sessions with any of these tags, if there are any
+    pub tags: Vec<String>,
+    /// Leave out work sessions with any of these tags, even if they match `tags`
+    pub excluded_tags: Vec<String>,
+    /// Leave out work sessions already billed by an invoice, and expenses up to its date
+    pub since_last_invoice: bool,
+    /// Columns of the work session table in this order instead of the full layout
+    pub columns: Option<Vec<Column>>,
+    /// Show durations as `H:MM:SS`, always the case for time sheets tracking seconds
+    pub seconds: bool,
+}
+
+impl AnalyzeOptions {
+    /// These options with the settings of the time sheet and the global configuration filled in
+    /// where no option was given.
+    fn with_defaults(&self, time_sheet: &TimeSheet) -> AnalyzeOptions {
+        AnalyzeOptions {
+            seconds: self.seconds || time_sheet.seconds_precision(),
+            currency: self
+                .currency
+                .clone()
+                .or_else(|| time_sheet.defaults.currency.clone()),
+            locale: self.locale.or(time_sheet.defaults.locale),
+            ..self.clone()
+        }
+    }
+
+    fn includes(&self, work_session: &WorkSession, now: DateTime<Local>) -> bool {
+        !(self.closed_only && work_session.stop.is_none())
+            && self.since.map_or(true, |since| work_session.start >= since)
+            && self
+                .homeoffice
+                .map_or(true, |homeoffice| work_session.homeoffice == homeoffice)
+            && (self.tags.is_empty() || self.tags.iter().any(|t| work_session.tags.contains(t)))
+            && !self
+                .excluded_tags
+                .iter()
+                .any(|t| work_session.tags.contains(t))
+            && self.min_duration.map_or(true, |minutes| {
+                work_session.duration(now) >= chrono::Duration::minutes(i64::from(minutes))
+            })
+    }
+
+    fn precision(&self) -> usize {
+        self.precision.unwrap_or(DEFAULT_PRECISION)
+    }
+
+    fn currency(&self) -> &str {
+        match &self.currency {
+            Some(c) => c,
+            None if self.plain => "EUR",
+            None => "€",
+        }
+    }
+
+    /// Format a number with the configured precision and locale.
+    fn format_number(&self, number: f32) -> String {
+        let formatted = format!("{:.*}", self.precision(), number);
+        match self.locale {
+            None | Some(Locale::En) => formatted,
+            Some(Locale::De) => {
+                let (sign, digits) = match formatted.strip_prefix('-') {
+                    Some(d) => ("-", d),
+                    None => ("", formatted.as_str()),
+                };
+                let (integer, fraction) = match digits.find('.') {
+                    Some(i) => (&digits[..i], Some(&digits[i + 1..])),
+                    None => (digits, None),
+                };
+                let mut grouped = String::new();
+                for (i, digit) in integer.chars().enumerate() {
+                    if i > 0 && (integer.len() - i) % 3 == 0 {
+                        grouped.push('.');
+                    }
+                    grouped.push(digit);
+                }
+                match fraction {
+                    Some(f) => format!("{}{},{}", sign, grouped, f),
+                    None => format!("{}{}", sign, grouped),
+                }
+            }
+        }
+    }
+
+    /// Format an amount of euros, converted into the requested currency
+    fn format_amount(&self, amount: f32) -> String {
+        self.format_number(amount * self.exchange_rate.unwrap_or(1f32))
+    }
+
+    fn format_money(&self, amount: f32) -> String {
+        if self.plain || self.currency.is_some() || self.locale == Some(Locale::De) {
+            format!("{} {}", self.format_amount(amount), self.currency())
+        } else {
+            format!("{}€", self.format_amount(amount))
+        }
+    }
+
+    /// The amount rounded as requested by `round_total`, still in the base currency.
+    fn rounded_total(&self, amount: f32) -> Option<f32> {
+        self.round_total.map(|unit| {
+            let exchange_rate = self.exchange_rate.unwrap_or(1f32);
+            (amount * exchange_rate / unit).round() * unit / exchange_rate
+        })
+    }
+
+    fn check_mark(&self) -> &'static str {
+        if self.plain {
+            "x"
+        } else {
+            "✔"
+        }
+    }
+
+    /// Table format to use, `default` unless plain output was requested
+    fn table_format(&self, default: format::TableFormat) -> format::TableFormat {
+        if self.plain {
+            *format::consts::FORMAT_CLEAN
+        } else {
+            default
+        }
+    }
+}
+
+impl TimeSheet {
+    /// Drop all work sessions not matching the given predicate.
+    ///
+    /// Returns the ID of every remaining work session.
+    fn filter_work_sessions<F>(&mut self, predicate: F) -> Vec<usize>
+    where
+        F: Fn(&WorkSession) -> bool,
+    {
+        let ids = self
+            .work_sessions
+            .iter()
+            .filter(|work_session| predicate(work_session))
+            .map(|work_session| work_session.id)
+            .collect();
+        self.work_sessions
+            .retain(|work_session| predicate(work_session));
+        ids
+    }
+}
+
+/// Convert a duration to decimal hours. Durations are only converted for display and billing, all
+/// sums are built from `chrono::Duration` to avoid floating point drift.
+fn hours(duration: chrono::Duration) -> f32 {
+    duration.num_seconds() as f32 / 3600f32
+}
+
+/// Format a duration as decimal hours or, if requested, as `H:MM`.
+fn format_duration(duration: chrono::Duration, options: &AnalyzeOptions) -> String {
+    if options.seconds {
+        let seconds = duration.num_seconds();
+        let sign = if seconds < 0 { "-" } else { "" };
+        let seconds = seconds.abs();
+        format!(
+            "{}{}:{:02}:{:02}",
+            sign,
+            seconds / 3600,
+            seconds / 60 % 60,
+            seconds % 60
+        )
+    } else if options.hours_minutes {
+        let minutes = duration.num_minutes();
+        let sign = if minutes < 0 { "-" } else { "" };
+        format!("{}{}:{:02}", sign, minutes.abs() / 60, minutes.abs() % 60)
+    } else {
+        options.format_number(hours(duration))
+    }
+}
+
+/// Format a duration of the totals table, as `Xh Ym` if `totals_hm` is set.
+fn format_total(duration: chrono::Duration, options: &AnalyzeOptions) -> String {
+    if options.totals_hm {
+        let minutes = duration.num_minutes();
+        let sign = if minutes < 0 { "-" } else { "" };
+        format!("{}{}h {}m", sign, minutes.abs() / 60, minutes.abs() % 60)
+    } else {
+        format!("{}h", format_duration(duration, options))
+    }
+}
+
+/// Build a table of durations with their labels, adding a cost column if `with_cost` is set.
+fn duration_table(
+    title: &str,
+    durations: Vec<(String, chrono::Duration, Option<f32>)>,
+    with_cost: bool,
+    options: &AnalyzeOptions,
+) -> Table {
+    let mut table = Table::new();
+    table.set_format(options.table_format(*format::consts::FORMAT_NO_BORDER_LINE_SEPARATOR));
+    if with_cost {
+        table.set_titles(row![
+            title,
+            "Time [h]",
+            format!("Cost [{}]", options.currency())
+        ]);
+    } else {
+        table.set_titles(row![title, "Time [h]"]);
+    }
+    for (label, duration, cost) in durations {
+        if with_cost {
+            table.add_row(row![
+                label,
+                r->format_duration(duration, options),
+                r->options.format_amount(cost.unwrap_or(0f32))
+            ]);
+        } else {
+            table.add_row(row![label, r->format!("{}h", format_duration(duration, options))]);
+        }
+    }
+    table
+}
+
+/// Escape backslashes, tabs and line breaks so a value fits into a single TSV field
+fn escape_tsv(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('\t', "\\t")
+        .replace('\n', "\\n")
+        .replace('\r', "\\r")
+}
+
+/// Build a table listing all work sessions of a time sheet, labeled with the given IDs.
+fn work_session_table(
+    time_sheet: &TimeSheet,
+    ids: &[usize],
+    now: DateTime<Local>,
+    options: &AnalyzeOptions,
+) -> Table {
+    let description_width = description_width(options.description_width);
+    let columns = match &options.columns {
+        Some(columns) => columns.clone(),
+        None => default_columns(time_sheet),
+    };
+    let with_cost = columns.contains(&Column::Cost);
+    let mut table = Table::new();
+    table.set_format(options.table_format(*format::consts::FORMAT_NO_BORDER_LINE_SEPARATOR));
+
+    let mut titles = Row::empty();
+    for column in &columns {
+        titles.add_cell(Cell::new(&match column {
+            Column::Id => String::from("ID"),
+            Column::Start => String::from("Start"),
+            Column::Stop => String::from("Stop"),
+            Column::Homeoffice => String::from("H"),
+            Column::Billable => String::from("B"),
+            Column::Time => String::from("Time [h]"),
+            Column::Fee => format!("Fee [{}]", options.currency()),
+            Column::Cost => format!("Cost [{}]", options.currency()),
+            Column::Description => String::from("Description"),
+        }));
+    }
+    table.set_titles(titles);
+
+    for (&i, work_session) in ids.iter().zip(time_sheet.work_sessions.iter()) {
+        let mark = |set: bool| if set { options.check_mark() } else { "" };
+        let mut work_session_row = Row::empty();
+        for column in &columns {
+            work_session_row.add_cell(match column {
+                Column::Id => Cell::new(&i.to_string()).style_spec("r"),
+                Column::Start => Cell::new(
+                    &work_session
+                        .start
+                        .format(time_sheet.datetime_format())
+                        .to_string(),
+                ),
+                Column::Stop => Cell::new(
+                    &work_session
+                        .stop
+                        .unwrap_or(now)
+                        .format(time_sheet.datetime_format())
+                        .to_string(),
+                ),
+                Column::Homeoffice => Cell::new(mark(work_session.homeoffice)),
+                Column::Billable => Cell::new(mark(work_session.billable)),
+                Column::Time => {
+                    let duration = format_duration(work_session.duration(now), options);
+                    let time = match with_cost {
+                        true => duration,
+                        false => format!("{}h", duration),
+                    };
+                    Cell::new(&time).style_spec("r")
+                }
+                Column::Fee => Cell::new(
+                    &work_session
+                        .fixed_fee
+                        .map(|f| options.format_amount(f))
+                        .unwrap_or_default(),
+                )
+                .style_spec("r"),
+                Column::Cost => Cell::new(
+                    &time_sheet
+                        .work_session_cost(work_session, now)
+                        .map(|c| options.format_amount(c))
+                        .unwrap_or_default(),
+                )
+                .style_spec("r"),
+                Column::Description => Cell::new(&split_description_string(
+                    &work_session.description,
+                    description_width,
+                )),
+            });
+        }
+        table.add_row(work_session_row);
+    }
+    table
+}
+
+/// The full layout of the work session table, with billing columns only if they have content.
+fn default_columns(time_sheet: &TimeSheet) -> Vec<Column> {
+    let with_cost = time_sheet.has_costs();
+    let with_fee = time_sheet
+        .work_sessions
+        .iter()
+        .any(|work_session| work_session.fixed_fee.is_some());
+    let mut columns = vec![Column::Id, Column::Start, Column::Stop, Column::Homeoffice];
+    if with_cost {
+        columns.push(Column::Billable);
+    }
+    columns.push(Column::Time);
+    if with_fee {
+        columns.push(Column::Fee);
+    }
+    if with_cost {
+        columns.push(Column::Cost);
+    }
+    columns.push(Column::Description);
+    columns
+}
+
+pub fn analyze_work_sheet(
+    _project: Option<&str>,
+    options: &AnalyzeOptions,
+    time_sheet_path: &Path,
+) -> Result<(), TimetrackerError> {
+    let mut time_sheet = TimeSheet::load(time_sheet_path)?;
+    let options = &options.with_defaults(&time_sheet);
+    let now = Local::now();
+    if handle_auto_stop(&mut time_sheet, options.auto_fix, now) {
+        time_sheet.save(time_sheet_path)?;
+    }
+    if time_sheet.work_sessions.is_empty()
+        && time_sheet.expenses.is_empty()
+        && !options.json
+        && !options.tsv
+        && !options.quiet
+    {
+        println!("No work sessions tracked yet.");
+        return Ok(());
+    }
+    let last_invoice_date = time_sheet
+        .last_invoice_date
+        .filter(|_| options.since_last_invoice);
+    let ids = time_sheet.filter_work_sessions(|work_session| {
+        options.includes(work_session, now)
+            && !(options.since_last_invoice && work_session.invoiced)
+    });
+    let total_duration = time_sheet.total_duration();
+    let work_time = hours(total_duration);
+    let project_cost = time_sheet.total_cost();
+    let homeoffice_map = time_sheet.homeoffice_days(now);
+    let homeoffice_durations = time_sheet.homeoffice_durations(now);
+
+    if options.json {
+        let work_sessions = ids
+            .iter()
+            .zip(time_sheet.work_sessions.iter())
+            .map(|(&i, work_session)| {
+                let duration = hours(work_session.duration(now));
+                WorkSessionSummary {
+                    id: i,
+                    start: work_session.start,
+                    stop: work_session.stop,
+                    duration,
+                    cost: time_sheet.work_session_cost(work_session, now),
+                    description: work_session.description.clone(),
+                    homeoffice: work_session.homeoffice,
+                    billable: work_session.billable,
+                    fixed_fee: work_session.fixed_fee,
+                    activity: work_session.activity,
+                }
+            })
+            .collect();
+        let summary = TimeSheetSummary {
+            project_name: time_sheet.project_name.clone(),
+            hourly_rate: time_sheet.rate_at(now.naive_local().date()),
+            total_work_time: work_time,
+            total_cost: project_cost,
+            days_worked: time_sheet.days_worked(),
+            homeoffice_days: homeoffice_map
+                .iter()
+                .map(|(year, dates)| (year.clone(), dates.len()))
+                .collect(),
+            homeoffice_hours: homeoffice_durations
+                .iter()
+                .map(|(year, &duration)| (year.clone(), hours(duration)))
+                .collect(),
+            work_sessions,
+        };
+        println!("{}", serde_json::to_string_pretty(&summary)?);
+        return Ok(());
+    }
+
+    if options.tsv {
+        println!("id\tstart\tstop\thours\thomeoffice\tbillable\tcost\tdescription");
+        for (&i, work_session) in ids.iter().zip(time_sheet.work_sessions.iter()) {
+            println!(
+                "{}\t{}\t{}\t{:.02}\t{}\t{}\t{}\t{}",
+                i,
+                work_session.start.format(time_sheet.datetime_format()),
+                work_session
+                    .stop
+                    .map(|s| s.format(time_sheet.datetime_format()).to_string())
+                    .unwrap_or_default(),
+                hours(work_session.duration(now)),
+                work_session.homeoffice,
+                work_session.billable,
+                time_sheet
+                    .work_session_cost(work_session, now)
+                    .map(|c| format!("{:.02}", c))
+                    .unwrap_or_default(),
+                escape_tsv(&work_session.description)
+            );
+        }
+        return Ok(());
+    }
+
+    if let Some(Breakdown::Weekday) = options.breakdown {
+        let weekday_costs = time_sheet.costs_by(now, |work_session| {
+            work_session.start.weekday().num_days_from_monday()
+        });
+        let mut weekday = Weekday::Mon;
+        let mut durations = Vec::new();
+        for (day, &duration) in time_sheet.weekday_durations(now).iter().enumerate() {
+            durations.push((
+                format!("{:?}", weekday),
+                duration,
+                weekday_costs.get(&(day as u32)).copied(),
+            ));
+            weekday = weekday.succ();
+        }
+        duration_table("weekday", durations, time_sheet.has_costs(), options).printstd();
+        return Ok(());
+    }
+
+    if let Some(Breakdown::Hour) = options.breakdown {
+        let durations = time_sheet.hour_durations(now);
+        let longest = durations
+            .iter()
+            .max()
+            .copied()
+            .unwrap_or_else(chrono::Duration::zero);
+        let mut hour_table = Table::new();
+        hour_table
+            .set_format(options.table_format(*format::consts::FORMAT_NO_BORDER_LINE_SEPARATOR));
+        hour_table.set_titles(row!["Hour", "Time [h]", ""]);
+        for (hour, duration) in durations.iter().enumerate() {
+            let width = if longest > chrono::Duration::zero() {
+                (duration.num_seconds() as f32 / longest.num_seconds() as f32
+                    * HISTOGRAM_WIDTH as f32)
+                    .round() as usize
+            } else {
+                0
+            };
+            hour_table.add_row(row![
+                format!("{:02}:00", hour),
+                r->format_duration(*duration, options),
+                "#".repeat(width)
+            ]);
+        }
+        hour_table.printstd();
+        return Ok(());
+    }
+
+    if options.gaps {
+        let threshold = chrono::Duration::minutes(i64::from(
+            options
+                .gap_threshold
+                .unwrap_or(DEFAULT_GAP_THRESHOLD_MINUTES),
+        ));
+        let gaps = time_sheet.gaps();
+        if gaps.is_empty() {
+            println!("No gaps between work sessions found.");
+            return Ok(());
+        }
+        let mut gap_table = Table::new();
+        gap_table
+            .set_format(options.table_format(*format::consts::FORMAT_NO_BORDER_LINE_SEPARATOR));
+        gap_table.set_titles(row!["Date", "From", "To", "Gap [h]"]);
+        for (from, to) in gaps {
+            let gap = to - from;
+            let mut gap_row = row![
+                from.format(DATE_FORMAT),
+                from.format("%H:%M"),
+                to.format("%H:%M"),
+                r->format_duration(gap, options)
+            ];
+            if gap >= threshold && !options.plain {
+                for cell in gap_row.iter_mut() {
+                    cell.style(Attr::ForegroundColor(color::RED));
+                }
+            }
+            gap_table.add_row(gap_row);
+        }
+        gap_table.printstd();
+        return Ok(());
+    }
+
+    if options.legal_check {
+        let violations = time_sheet.legal_violations(now);
+        if violations.is_empty() {
+            println!("No violations of the working time limits found.");
+            return Ok(());
+        }
+        let mut violation_table = Table::new();
+        violation_table
+            .set_format(options.table_format(*format::consts::FORMAT_NO_BORDER_LINE_SEPARATOR));
+        violation_table.set_titles(row!["Date", "Violation", "Time [h]", "Limit [h]"]);
+        for (date, violation, duration, limit) in violations {
+            violation_table.add_row(row![
+                date.format(DATE_FORMAT),
+                violation,
+                r->format_duration(duration, options),
+                r->format_duration(limit, options)
+            ]);
+        }
+        violation_table.printstd();
+        return Ok(());
+    }
+
+    if options.reconcile {
+        let increment = match time_sheet.billing_increment_minutes {
+            Some(i) => i,
+            None => {
+                println!("No billing increment configured, billed time equals tracked time.");
+                return Ok(());
+            }
+        };
+        let mut reconcile_table = Table::new();
+        reconcile_table
+            .set_format(options.table_format(*format::consts::FORMAT_NO_BORDER_LINE_SEPARATOR));
+        reconcile_table.set_titles(row![
+            "ID",
+            "Start",
+            "Tracked [h]",
+            "Billed [h]",
+            "Difference [h]"
+        ]);
+        let mut tracked_total = chrono::Duration::zero();
+        let mut billed_total = chrono::Duration::zero();
+        for (work_session, tracked, billed) in time_sheet.reconciled_durations(now) {
+            tracked_total = tracked_total + tracked;
+            billed_total = billed_total + billed;
+            reconcile_table.add_row(row![
+                r->work_session.id,
+                work_session.start.format(time_sheet.datetime_format()),
+                r->format_duration(tracked, options),
+                r->format_duration(billed, options),
+                r->format_duration(billed - tracked, options)
+            ]);
+        }
+        reconcile_table.add_row(row![
+            "",
+            format!("Total ({} minute increments)", increment),
+            r->format_duration(tracked_total, options),
+            r->format_duration(billed_total, options),
+            r->format_duration(billed_total - tracked_total, options)
+        ]);
+        reconcile_table.printstd();
+        return Ok(());
+    }
+
+    if options.quiet {
+        match project_cost {
+            Some(c) => println!(
+                "{}\t{}",
+                format_duration(total_duration, options),
+                options.format_amount(c)
+            ),
+            None => println!("{}", format_duration(total_duration, options)),
+        }
+        return Ok(());
+    }
+
+    let mut project_table = Table::new();
+    project_table.set_format(options.table_format(*format::consts::FORMAT_DEFAULT));
+    project_table.add_row(row!["Project", time_sheet.project_name]);
+    if !time_sheet.notes.is_empty() {
+        project_table.add_row(row!["Notes", time_sheet.notes]);
+    }
+    for (since, r) in &time_sheet.rate_changes {
+        project_table.add_row(row![
+            if *since == INITIAL_RATE_DATE {
+                String::from("Hourly Rate")
+            } else {
+                format!("Hourly Rate since {}", since.format(DATE_FORMAT))
+            },
+            r->options.format_money(*r)
+        ]);
+    }
+    project_table.printstd();
+
+    println!();
+
+    work_session_table(&time_sheet, &ids, now, options).printstd();
+
+    println!();
+
+    let mut homeoffice_table = Table::new();
+    homeoffice_table
+        .set_format(options.table_format(*format::consts::FORMAT_NO_BORDER_LINE_SEPARATOR));
+    homeoffice_table.set_titles(row!["year", "days in homeoffice", "hours in homeoffice"]);
+    for (year, dates) in &homeoffice_map {
+        let duration = homeoffice_durations
+            .get(year)
+            .copied()
+            .unwrap_or_else(chrono::Duration::zero);
+        homeoffice_table.add_row(row![
+            year,
+            r->dates.len(),
+            r->format_duration(duration, options)
+        ]);
+    }
+    homeoffice_table.printstd();
+
+    println!();
+
+    let yearly_costs = time_sheet.costs_by(now, |work_session| {
+        format!("{}", work_session.start.format("%Y"))
+    });
+    duration_table(
+        "year",
+        time_sheet
+            .yearly_durations(now)
+            .into_iter()
+            .map(|(year, duration)| {
+                let cost = yearly_costs.get(&year).copied();
+                (year, duration, cost)
+            })
+            .collect(),
+        time_sheet.has_costs(),
+        options,
+    )
+    .printstd();
+
+    println!();
+
+    if !time_sheet.activities.is_empty() {
+        let activity_costs = time_sheet.costs_by(now, |work_session| work_session.activity);
+        let activity_durations = time_sheet
+            .activity_durations(now)
+            .into_iter()
+            .map(|(activity, duration)| {
+                let name = match activity {
+                    Some(id) => time_sheet
+                        .activities
+                        .iter()
+                        .find(|a| a.id == id)
+                        .map(|a| a.name.clone())
+                        .unwrap_or_else(|| format!("unknown ({})", id)),
+                    None => String::from("none"),
+                };
+                (name, duration, activity_costs.get(&activity).copied())
+            })
+            .collect();
+        duration_table(
+            "activity",
+            activity_durations,
+            time_sheet.has_costs(),
+            options,
+        )
+        .printstd();
+
+        println!();
+    }
+
+    if !time_sheet.subprojects.is_empty() {
+        let subproject_totals = time_sheet.subproject_totals(now);
+        let mut subprojects: Vec<&SubProject> = time_sheet.subprojects.iter().collect();
+        subprojects.sort();
+        let subproject_durations = subprojects
+            .into_iter()
+            .filter_map(|subproject| {
+                subproject_totals
+                    .get(&subproject.id)
+                    .map(|(duration, cost)| {
+                        let depth = time_sheet.subproject_ancestors(subproject.id).len() - 1;
+                        (
+                            format!("{}{}", "  ".repeat(depth), subproject.name),
+                            *duration,
+                            *cost,
+                        )
+                    })
+            })
+            .collect();
+        duration_table(
+            "subproject",
+            subproject_durations,
+            time_sheet.has_costs(),
+            options,
+        )
+        .printstd();
+
+        println!();
+    }
+
+    if let Some(stats) = time_sheet.session_stats(now) {
+        let mut stats_table = Table::new();
+        stats_table.set_format(options.table_format(*format::consts::FORMAT_DEFAULT));
+        stats_table.add_row(row![
+            "Mean session",
+            "",
+            r->format!("{}h", format_duration(stats.mean, options))
+        ]);
+        stats_table.add_row(row![
+            "Median session",
+            "",
+            r->format!("{}h", format_duration(stats.median, options))
+        ]);
+        for (label, (duration, start)) in &[
+            ("Longest session", stats.longest),
+            ("Shortest session", stats.shortest),
+        ] {
+            stats_table.add_row(row![
+                label,
+                start.format(DATE_FORMAT),
+                r->format!("{}h", format_duration(*duration, options))
+            ]);
+        }
+        stats_table.printstd();
+
+        println!();
+    }
+
+    let mut total_table = Table::new();
+    total_table.set_format(options.table_format(*format::consts::FORMAT_DEFAULT));
+    total_table.add_row(row![
+        "Total work time",
+        r->format_total(total_duration, options)
+    ]);
+    if let Some(estimate) = time_sheet.estimated_hours {
+        let tracked = total_duration.num_seconds() as f32 / 3600.;
+        let estimated_duration = chrono::Duration::seconds((estimate * 3600.).round() as i64);
+        total_table.add_row(row![
+            "Estimate",
+            r->match options.totals_hm {
+                true => format_total(estimated_duration, options),
+                false => format!("{}h", options.format_number(estimate)),
+            }
+        ]);
+        total_table.add_row(row!["Progress", r->progress_bar(tracked, estimate)]);
+        if tracked > estimate {
+            total_table.add_row(row![
+                "Over estimate",
+                r->match options.totals_hm {
+                    true => format_total(total_duration - estimated_duration, options),
+                    false => format!("{}h", options.format_number(tracked - estimate)),
+                }
+            ]);
+        }
+    }
+    total_table.add_row(row!["Days worked", r->time_sheet.days_worked()]);
+    if time_sheet.billing_increment_minutes.is_some() {
+        let billed_duration = time_sheet
+            .work_sessions
+            .iter()
+            .filter(|work_session| work_session.billable)
+            .fold(chrono::Duration::zero(), |total, work_session| {
+                total + time_sheet.billed_duration(work_session, now)
+            });
+        total_table.add_row(row![
+            "Billed work time",
+            r->format_total(billed_duration, options)
+        ]);
+    }
+    if let Some(price) = time_sheet.fixed_price {
+        total_table.add_row(row!["Fixed price", r->options.format_money(price)]);
+        if total_duration > chrono::Duration::zero() {
+            total_table.add_row(row![
+                "Effective hourly rate",
+                r->format!("{}/h", options.format_money(price / work_time))
+            ]);
+        }
+    } else if let Some(c) = project_cost {
+        let mut cost_row = row![
+            "Total project cost",
+            r->options.format_money(c)
+        ];
+        if time_sheet.budget.map_or(false, |budget| c > budget) && !options.plain {
+            for cell in cost_row.iter_mut() {
+                cell.style(Attr::ForegroundColor(color::RED));
+            }
+        }
+        total_table.add_row(cost_row);
+        if let Some(rounded) = options.rounded_total(c) {
+            total_table.add_row(row![
+                "Rounding adjustment",
+                r->options.format_money(rounded - c)
+            ]);
+            total_table.add_row(row!["Rounded total", r->options.format_money(rounded)]);
+        }
+        if let Some(budget) = time_sheet.budget {
+            total_table.add_row(row!["Budget", r->options.format_money(budget)]);
+            total_table.add_row(row![
+                "Remaining budget",
+                r->options.format_money(budget - c)
+            ]);
+        }
+    }
+    let expenses: f32 = time_sheet
+        .expenses
+        .iter()
+        .filter(|expense| {
+            options
+                .since
+                .map_or(true, |since| expense.date >= since.naive_local().date())
+                && last_invoice_date.map_or(true, |date| expense.date > date)
+        })
+        .map(|expense| expense.amount)
+        .sum();
+    if expenses > 0. {
+        total_table.add_row(row!["Expenses", r->options.format_money(expenses)]);
+        if let Some(c) = time_sheet.fixed_price.or(project_cost) {
+            total_table.add_row(row![
+                "Total incl. expenses",
+                r->options.format_money(c + expenses)
+            ]);
+        }
+    }
+    total_table.printstd();
+    Ok(())
+}
+
+/// Print all work sessions started today and the time worked today
+pub fn analyze_today(time_sheet_path: &Path) -> Result<(), TimetrackerError> {
+    let mut time_sheet = TimeSheet::load(time_sheet_path)?;
+    let now = Local::now();
+    let today = now.date();
+    let ids = time_sheet.filter_work_sessions(|work_session| work_session.start.date() == today);
+    if time_sheet.work_sessions.is_empty() {
+        println!("No work sessions tracked today.");
+        return Ok(());
+    }
+
+    let options = AnalyzeOptions::default().with_defaults(&time_sheet);
+    work_session_table(&time_sheet, &ids, now, &options).printstd();
+
+    println!();
+
+    let work_time = hours(time_sheet.total_duration());
+    match time_sheet.total_cost() {
+        Some(c) => println!(
+            "Total today: {:.02}h ({})",
+            work_time,
+            options.format_money(c)
+        ),
+        None => println!("Total today: {:.02}h", work_time),
+    }
+    Ok(())
+}
+
+/// Print all billable work sessions not invoiced yet that stopped up to `date`, today if not
+/// given, together with the expenses since the last invoice, and mark them as invoiced.
+///
+/// Running work sessions are left for the next invoice. Nothing is changed if there is nothing to
+/// invoice.
+pub fn create_invoice(date: Option<&str>, time_sheet_path: &Path) -> Result<(), TimetrackerError> {
+    let mut time_sheet = TimeSheet::load(time_sheet_path)?;
+    let now = Local::now();
+    let date = match date {
+        Some(d) => parse_date(d)?,
+        None => now.naive_local().date(),
+    };
+    let last_invoice_date = time_sheet.last_invoice_date;
+    if let Some(last) = last_invoice_date.filter(|&last| date < last) {
+        return Err(TimetrackerError::TimeSheet(format!(
+            "The last invoice already covers everything up to {}!",
+            last.format(DATE_FORMAT)
+        )));
+    }
+
+    let ids: Vec<usize> = time_sheet
+        .work_sessions
+        .iter()
+        .filter(|work_session| {
+            work_session.billable
+                && !work_session.invoiced
+                && work_session
+                    .stop
+                    .map_or(false, |stop| stop.naive_local().date() <= date)
+        })
+        .map(|work_session| work_session.id)
+        .collect();
+    let expenses: Vec<Expense> = time_sheet
+        .expenses
+        .iter()
+        .filter(|expense| {
+            expense.date <= date && last_invoice_date.map_or(true, |last| expense.date > last)
+        })
+        .cloned()
+        .collect();
+    if ids.is_empty() && expenses.is_empty() {
+        return Err(TimetrackerError::TimeSheet(format!(
+            "Nothing to invoice up to {}!",
+            date.format(DATE_FORMAT)
+        )));
+    }
+
+    for &id in &ids {
+        time_sheet.get_work_session_mut(id)?.invoiced = true;
+    }
+    time_sheet.last_invoice_date = Some(date);
+    time_sheet.save(time_sheet_path)?;
+
+    let options = AnalyzeOptions::default().with_defaults(&time_sheet);
+    time_sheet.filter_work_sessions(|work_session| ids.contains(&work_session.id));
+    println!(
+        "Invoice for {} up to {}",
+        time_sheet.project_name,
+        date.format(DATE_FORMAT)
+    );
+    println!();
+    if !ids.is_empty() {
+        work_session_table(&time_sheet, &ids, now, &options).printstd();
+        println!();
+    }
+    if !expenses.is_empty() {
+        expense_table(&expenses.iter().collect::<Vec<_>>(), &options).printstd();
+        println!();
+    }
+    let work_time = hours(time_sheet.total_duration());
+    let expenses_total: f32 = expenses.iter().map(|expense| expense.amount).sum();
+    match time_sheet.total_cost() {
+        Some(c) if expenses_total > 0. => println!(
+            "Total: {:.02}h ({}) plus {} expenses, {} in total",
+            work_time,
+            options.format_money(c),
+            options.format_money(expenses_total),
+            options.format_money(c + expenses_total)
+        ),
+        Some(c) => println!("Total: {:.02}h ({})", work_time, options.format_money(c)),
+        None if expenses_total > 0. => println!(
+            "Total: {:.02}h plus {} expenses",
+            work_time,
+            options.format_money(expenses_total)
+        ),
+        None => println!("Total: {:.02}h", work_time),
+    }
+    Ok(())
+}
+
+fn expense_table(expenses: &[&Expense], options: &AnalyzeOptions) -> Table {
+    let mut table = Table::new();
+    table.set_format(*format::consts::FORMAT_NO_BORDER_LINE_SEPARATOR);
+    table.set_titles(row![
+        "Date",
+        format!("Amount [{}]", options.currency()),
+        "Description"
+    ]);
+    for expense in expenses {
+        table.add_row(row![
+            expense.date.format(DATE_FORMAT),
+            r->options.format_amount(expense.amount),
+            expense.description
+        ]);
+    }
+    table
+}
+
+/// Record an expense on `date`, today if not given
+pub fn add_expense(
+    date: Option<&str>,
+    amount: f32,
+    description: &str,
+    time_sheet_path: &Path,
+) -> Result<(), TimetrackerError> {
+    if !amount.is_finite() || amount <= 0. {
+        return Err(TimetrackerError::TimeSheet(format!(
+            "Invalid amount {}! It must be a positive number.",
+            amount
+        )));
+    }
+    let date = match date {
+        Some(d) => parse_date(d)?,
+        None => Local::now().naive_local().date(),
+    };
+    let mut time_sheet = TimeSheet::load(time_sheet_path)?;
+    println!(
+        "Adding an expense of {} on {}",
+        AnalyzeOptions::default()
+            .with_defaults(&time_sheet)
+            .format_money(amount),
+        date.format(DATE_FORMAT)
+    );
+    let position = time_sheet
+        .expenses
+        .iter()
+        .position(|expense| expense.date > date)
+        .unwrap_or(time_sheet.expenses.len());
+    time_sheet.expenses.insert(
+        position,
+        Expense::new(date, description.to_string(), amount),
+    );
+    time_sheet.save(time_sheet_path)?;
+    Ok(())
+}
+
+/// Print all expenses and their total
+pub fn list_expenses(time_sheet_path: &Path) -> Result<(), TimetrackerError> {
+    let time_sheet = TimeSheet::load(time_sheet_path)?;
+    if time_sheet.expenses.is_empty() {
+        println!("No expenses recorded yet.");
+        return Ok(());
+    }
+    let options = AnalyzeOptions::default().with_defaults(&time_sheet);
+    let expenses: Vec<&Expense> = time_sheet.expenses.iter().collect();
+    expense_table(&expenses, &options).printstd();
+    println!();
+    let total: f32 = expenses.iter().map(|expense| expense.amount).sum();
+    println!("Total expenses: {}", options.format_money(total));
+    Ok(())
+}
+
+/// Print the amount owed for all closed billable work sessions not invoiced yet as a single line
+pub fn print_total_due(
+    options: &AnalyzeOptions,
+    time_sheet_path: &Path,
+) -> Result<(), TimetrackerError> {
+    let time_sheet = TimeSheet::load(time_sheet_path)?;
+    println!("{}", total_due(time_sheet, options, Local::now())?);
+    Ok(())
+}
+
+/// The line `print_total_due` prints for the given time sheet
+fn total_due(
+    mut time_sheet: TimeSheet,
+    options: &AnalyzeOptions,
+    now: DateTime<Local>,
+) -> Result<String, TimetrackerError> {
+    if !time_sheet.has_costs() {
+        return Err(TimetrackerError::TimeSheet(String::from(
+            "No hourly rate set! Use `config --rate` to set one.",
+        )));
+    }
+    let options = &options.with_defaults(&time_sheet);
+    time_sheet.filter_work_sessions(|work_session| {
+        work_session.billable && !work_session.invoiced && work_session.stop.is_some()
+    });
+    let billed = time_sheet
+        .work_sessions
+        .iter()
+        .fold(chrono::Duration::zero(), |total, work_session| {
+            total + time_sheet.billed_duration(work_session, now)
+        });
+    let total = time_sheet.total_cost().unwrap_or(0.);
+    let billed_hours = hours(billed);
+    if billed_hours > 0. {
+        Ok(format!(
+            "Total due: {} ({}h @ {})",
+            options.format_money(total),
+            options.format_number(billed_hours),
+            options.format_money(total / billed_hours)
+        ))
+    } else {
+        Ok(format!("Total due: {}", options.format_money(total)))
+    }
+}
+
+/// Print the `count` most recent work sessions, the newest last
+pub fn log_work_sessions(count: usize, time_sheet_path: &Path) -> Result<(), TimetrackerError> {
+    let mut time_sheet = TimeSheet::load(time_sheet_path)?;
+    let now = Local::now();
+    let first = time_sheet.work_sessions.len().saturating_sub(count);
+    if let Some(cutoff) = time_sheet.work_sessions.get(first).map(|w| w.start) {
+        let ids = time_sheet.filter_work_sessions(|work_session| work_session.start >= cutoff);
+        let options = AnalyzeOptions::default().with_defaults(&time_sheet);
+        work_session_table(&time_sheet, &ids, now, &options).printstd();
+    } else {
+        println!("No work sessions tracked yet.");
+    }
+    Ok(())
+}
+
+/// Warn about a running work session exceeding `auto_stop_after_hours` or, with `auto_fix`, stop
+/// it at the cutoff. Returns whether the time sheet was changed.
+///
+/// Messages go to stderr to keep machine readable output intact.
+fn handle_auto_stop(time_sheet: &mut TimeSheet, auto_fix: bool, now: DateTime<Local>) -> bool {
+    let cutoff = match time_sheet.auto_stop_time(now) {
+        Some(c) => c,
+        None => return false,
+    };
+    if auto_fix {
+        if let Some(work_session) = time_sheet.work_sessions.last_mut() {
+            work_session.stop = Some(cutoff);
+        }
+        eprintln!(
+            "Stopped the running work session at {}",
+            cutoff.format(time_sheet.datetime_format())
+        );
+        true
+    } else {
+        eprintln!(
+            "Warning: the running work session has been going on for more than {} hours! \
+             Use --auto-fix to stop it at {}.",
+            time_sheet.auto_stop_after_hours.unwrap_or(0.),
+            cutoff.format(time_sheet.datetime_format())
+        );
+        false
+    }
+}
+
+/// Describe the running work session and the time worked today.
+fn status_string(time_sheet: &TimeSheet, now: DateTime<Local>) -> String {
+    let options = AnalyzeOptions::default().with_defaults(time_sheet);
+    let mut status = match time_sheet.work_sessions.last() {
+        Some(work_session) if work_session.stop.is_none() => format!(
+            "Working{} since {} ({}h)\n",
+            if work_session.description.is_empty() {
+                String::new()
+            } else {
+                format!(" on {}", work_session.description)
+            },
+            work_session.start.format(time_sheet.datetime_format()),
+            format_duration(work_session.duration(now), &options)
+        ),
+        _ => String::from("Not working right now\n"),
+    };
+    let today = time_sheet
+        .work_sessions
+        .iter()
+        .filter(|work_session| work_session.start.date() == now.date())
+        .fold(chrono::Duration::zero(), |total, work_session| {
+            total + work_session.duration(now)
+        });
+    status.push_str(&format!("Today: {}h\n", format_duration(today, &options)));
+    status
+}
+
+/// Print the running work session and the time worked today
+///
+/// With `auto_fix`, a forgotten work session running longer than configured is stopped.
+pub fn print_status(auto_fix: bool, time_sheet_path: &Path) -> Result<(), TimetrackerError> {
+    let mut time_sheet = TimeSheet::load(time_sheet_path)?;
+    let now = Local::now();
+    if handle_auto_stop(&mut time_sheet, auto_fix, now) {
+        time_sheet.save(time_sheet_path)?;
+    }
+    print!("{}", status_string(&time_sheet, now));
+    Ok(())
+}
+
+/// Print the status every `interval` seconds, clearing the screen in between.
+///
+/// The time sheet is only read, so interrupting this with Ctrl-C at any time is safe. Errors
+/// while reading it, e.g. because it is just being written, are shown until the next update.
+pub fn watch_status(interval: u64, time_sheet_path: &Path) -> Result<(), TimetrackerError> {
+    loop {
+        print!("\x1B[2J\x1B[H");
+        match TimeSheet::load(time_sheet_path) {
+            Ok(mut time_sheet) => {
+                let now = Local::now();
+                handle_auto_stop(&mut time_sheet, false, now);
+                print!("{}", status_string(&time_sheet, now));
+            }
+            Err(e) => println!("{}", e),
+        }
+        std::io::stdout().flush()?;
+        std::thread::sleep(std::time::Duration::from_secs(interval));
+    }
+}
+
+/// A single line summarizing a work session, as printed by `tail`.
+fn work_session_line(work_session: &WorkSession, datetime_format: &str) -> String {
+    let start = work_session.start.format(datetime_format).to_string();
+    format!(
+        "{:>4} | {} - {} |{}| {}",
+        work_session.id,
+        start,
+        match work_session.stop {
+            Some(s) => s.format(datetime_format).to_string(),
+            None => format!("{:<1$}", "running", start.len()),
+        },
+        if work_session.homeoffice { "H" } else { " " },
+        work_session.description
+    )
+}
+
+/// Print the latest work session, then every work session that is added, changed or removed
+/// by another process until interrupted with Ctrl-C.
+///
+/// The time sheet is polled for a new modification time and only ever read.
+pub fn tail_work_sessions(time_sheet_path: &Path) -> Result<(), TimetrackerError> {
+    let modified = |path: &Path| std::fs::metadata(path).and_then(|m| m.modified()).ok();
+    let mut last_modified = modified(time_sheet_path);
+    let mut previous = TimeSheet::load(time_sheet_path)?;
+    if let Some(work_session) = previous.work_sessions.iter().max_by_key(|w| w.id) {
+        println!(
+            "{}",
+            work_session_line(work_session, previous.datetime_format())
+        );
+    }
+    loop {
+        std::io::stdout().flush()?;
+        std::thread::sleep(std::time::Duration::from_secs(TAIL_POLL_SECONDS));
+        let current_modified = modified(time_sheet_path);
+        if current_modified == last_modified {
+            continue;
+        }
+        last_modified = current_modified;
+        // The file might just be written, try again with the next change
+        let current = match TimeSheet::load(time_sheet_path) {
+            Ok(t) => t,
+            Err(_) => continue,
+        };
+        for work_session in &current.work_sessions {
+            let unchanged = previous.work_sessions.iter().any(|w| {
+                w.id == work_session.id
+                    && w == work_session
+                    && w.homeoffice == work_session.homeoffice
+            });
+            if !unchanged {
+                println!(
+                    "{}",
+                    work_session_line(work_session, current.datetime_format())
+                );
+            }
+        }
+        for work_session in &previous.work_sessions {
+            if !current
+                .work_sessions
+                .iter()
+                .any(|w| w.id == work_session.id)
+            {
+                println!("Removed work session {}", work_session.id);
+            }
+        }
+        previous = current;
+    }
+}
+
+/// Print all work sessions without a stop time together with their elapsed time.
+pub fn list_open_sessions(time_sheet_path: &Path) -> Result<(), TimetrackerError> {
+    let mut time_sheet = TimeSheet::load(time_sheet_path)?;
+    let now = Local::now();
+    let ids = time_sheet.filter_work_sessions(|work_session| work_session.stop.is_none());
+    if time_sheet.work_sessions.is_empty() {
+        println!("No open work sessions found.");
+        return Ok(());
+    }
+
+    let mut table = Table::new();
+    table.set_format(*format::consts::FORMAT_NO_BORDER_LINE_SEPARATOR);
+    let options = AnalyzeOptions::default().with_defaults(&time_sheet);
+    table.set_titles(row!["ID", "Start", "Elapsed [h]", "Description"]);
+    for (&i, work_session) in ids.iter().zip(time_sheet.work_sessions.iter()) {
+        table.add_row(row![
+            r->i,
+            work_session.start.format(time_sheet.datetime_format()),
+            r->format_duration(work_session.duration(now), &options),
+            work_session.description
+        ]);
+    }
+    table.printstd();
+    Ok(())
+}
+
+/// Print where the time sheet is expected and whether it is in a usable state.
+///
+/// Problems with the time sheet are part of the report instead of being returned as errors.
+pub fn doctor(time_sheet_path: &Path) -> Result<(), TimetrackerError> {
+    let mut table = Table::new();
+    table.set_format(*format::consts::FORMAT_CLEAN);
+    table.add_row(row!["Time sheet", time_sheet_path.display()]);
+    let exists = time_sheet_path.exists();
+    table.add_row(row!["Exists", if exists { "yes" } else { "no" }]);
+    table.add_row(row![
+        "Global config",
+        match GlobalConfig::path() {
+            Some(p) if p.exists() => match GlobalConfig::load() {
+                Ok(_) => format!("{}", p.display()),
+                Err(e) => format!("{}: {}", p.display(), e),
+            },
+            Some(p) => format!("{} (not present)", p.display()),
+            None => String::from("none"),
+        }
+    ]);
+    if exists {
+        match TimeSheet::load(time_sheet_path) {
+            Ok(time_sheet) => {
+                table.add_row(row!["Parses", "yes"]);
+                table.add_row(row!["Project", time_sheet.project_name]);
+                table.add_row(row!["Work sessions", time_sheet.work_sessions.len()]);
+                let open = time_sheet
+                    .work_sessions
+                    .iter()
+                    .filter(|work_session| work_session.stop.is_none())
+                    .count();
+                table.add_row(row!["Open work sessions", open]);
+                let future = time_sheet.future_work_sessions(Local::now());
+                table.add_row(row![
+                    "Future starts",
+                    if future.is_empty() {
+                        String::from("none")
+                    } else {
+                        future
+                            .iter()
+                            .map(|id| id.to_string())
+                            .collect::<Vec<_>>()
+                            .join(", ")
+                    }
+                ]);
+                table.add_row(row![
+                    "Overlaps",
+                    match time_sheet.check_overlaps() {
+                        Ok(()) => String::from("none"),
+                        Err(e) => e.to_string(),
+                    }
+                ]);
+                table.add_row(row![
+                    "Schema version",
+                    if time_sheet.schema_version == SCHEMA_VERSION {
+                        format!("{}", time_sheet.schema_version)
+                    } else {
+                        format!(
+                            "{} (current is {})",
+                            time_sheet.schema_version, SCHEMA_VERSION
+                        )
+                    }
+                ]);
+            }
+            Err(e) => {
+                table.add_row(row!["Parses", format!("no: {}", e)]);
+            }
+        }
+    }
+    table.printstd();
+    Ok(())
+}
+
+#[allow(clippy::too_many_arguments)]
+pub fn add_work_session_to_time_sheet(
+    _project: Option<&str>,
+    start: &str,
+    stop: Option<&str>,
+    description: Option<&str>,
+    homeoffice: Option<bool>,
+    activity: Option<usize>,
+    subproject: Option<usize>,
+    billable: bool,
+    fixed_fee: Option<f32>,
+    snap: Option<u32>,
+    time_sheet_path: &Path,
+) -> Result<(), TimetrackerError> {
+    let mut time_sheet = TimeSheet::load(time_sheet_path)?;
+    let snap_time = |time| match snap {
+        Some(grid) => snap_to_grid(time, grid),
+        None => time,
+    };
+    let mut work_session = WorkSession::new(
+        snap_time(parse_datetime(start)?),
+        match stop {
+            Some(s) => Some(snap_time(parse_datetime(s)?)),
+            None => None,
+        },
+        match description {
+            Some(d) => String::from(d),
+            None => String::from(""),
+        },
+        homeoffice.unwrap_or(time_sheet.default_homeoffice()),
+    );
+    work_session.activity = activity;
+    work_session.subproject = subproject;
+    work_session.billable = billable;
+    work_session.fixed_fee = fixed_fee;
+
+    if work_session.start > Local::now() {
+        return Err(TimetrackerError::TimeSheet(format!(
+            "Work session would start in the future at {}!",
+            work_session.start.format(time_sheet.datetime_format())
+        )));
+    }
+    if let Some(a) = activity {
+        time_sheet.check_activity(a)?;
+    }
+    if let Some(s) = subproject {
+        time_sheet.check_subproject(s)?;
+    }
+    work_session.id = time_sheet.new_session_id();
+    time_sheet.work_sessions.push(work_session);
+    time_sheet.work_sessions.sort();
+    time_sheet.save(time_sheet_path)?;
+    Ok(())
+}
+
+/// Edit a work session, leaving every value not given unchanged
+#[allow(clippy::too_many_arguments)]
+pub fn edit_work_session(
+    id: usize,
+    start: Option<&str>,
+    stop: Option<&str>,
+    shift: Option<&str>,
+    description: Option<&str>,
+    homeoffice: Option<bool>,
+    fixed_fee: Option<f32>,
+    time_sheet_path: &Path,
+) -> Result<(), TimetrackerError> {
+    let mut time_sheet = TimeSheet::load(time_sheet_path)?;
+    let start = match start {
+        Some(s) => Some(parse_datetime(s)?),
+        None => None,
+    };
+    if let Some(s) = start {
+        if s > Local::now() {
+            return Err(TimetrackerError::TimeSheet(format!(
+                "Work session would start in the future at {}!",
+                s.format(time_sheet.datetime_format())
+            )));
+        }
+    }
+    let work_session = time_sheet.get_work_session_mut(id)?;
+    if let Some(s) = start {
+        work_session.start = s;
+    }
+    if let Some(s) = stop {
+        work_session.stop = Some(parse_datetime(s)?);
+    }
+    if let Some(s) = shift {
+        let offset = parse_shift(s)?;
+        let out_of_range = || {
+            TimetrackerError::TimeSheet(String::from("The shifted work session is out of range!"))
+        };
+        work_session.start = work_session
+            .start
+            .checked_add_signed(offset)
+            .ok_or_else(out_of_range)?;
+        work_session.stop = match work_session.stop {
+            Some(stop) => Some(stop.checked_add_signed(offset).ok_or_else(out_of_range)?),
+            None => None,
+        };
+        if work_session.stop.unwrap_or(work_session.start) > Local::now() {
+            return Err(TimetrackerError::TimeSheet(String::from(
+                "The shifted work session would end in the future!",
+            )));
+        }
+    }
+    if let Some(d) = description {
+        work_session.description = d.to_string();
+    }
+    if let Some(h) = homeoffice {
+        work_session.homeoffice = h;
+    }
+    if let Some(f) = fixed_fee {
+        work_session.fixed_fee = if f == 0f32 { None } else { Some(f) };
+    }
+    if let Some(s) = work_session.stop {
+        if s < work_session.start {
+            return Err(TimetrackerError::TimeSheet(String::from(
+                "Stop time lies before start time!",
+            )));
+        }
+    }
+    time_sheet.work_sessions.sort();
+    time_sheet.check_overlaps()?;
+    time_sheet.save(time_sheet_path)?;
+    Ok(())
+}
+
+/// Add tags to a work session, or remove them with `remove`
+pub fn tag_work_session(
+    id: usize,
+    tags: &[&str],
+    remove: bool,
+    time_sheet_path: &Path,
+) -> Result<(), TimetrackerError> {
+    let mut time_sheet = TimeSheet::load(time_sheet_path)?;
+    let work_session = time_sheet.get_work_session_mut(id)?;
+    for tag in tags {
+        if remove {
+            work_session.tags.retain(|t| t != tag);
+        } else if !work_session.tags.iter().any(|t| t == tag) {
+            work_session.tags.push(tag.to_string());
+        }
+    }
+    println!(
+        "Tags of work session {}: {}",
+        id,
+        work_session.tags.join(", ")
+    );
+    time_sheet.save(time_sheet_path)?;
+    Ok(())
+}
+
+/// Set or clear the homeoffice flag of a work session
+pub fn set_homeoffice(
+    id: usize,
+    homeoffice: bool,
+    time_sheet_path: &Path,
+) -> Result<(), TimetrackerError> {
+    let mut time_sheet = TimeSheet::load(time_sheet_path)?;
+    let work_session = time_sheet.get_work_session_mut(id)?;
+    work_session.homeoffice = homeoffice;
+    let start = work_session.start;
+    println!(
+        "{} work session {} starting at {} as homeoffice",
+        if homeoffice { "Marked" } else { "Unmarked" },
+        id,
+        start.format(time_sheet.datetime_format())
+    );
+    time_sheet.save(time_sheet_path)?;
+    Ok(())
+}
+
+/// Report duplicated work sessions and, with `fix`, remove all but the first of each
+pub fn dedupe_work_sessions(
+    fix: bool,
+    assume_yes: bool,
+    time_sheet_path: &Path,
+) -> Result<(), TimetrackerError> {
+    let mut time_sheet = TimeSheet::load(time_sheet_path)?;
+    let duplicates = time_sheet.duplicate_work_sessions();
+    if duplicates.is_empty() {
+        println!("No duplicated work sessions found.");
+        return Ok(());
+    }
+    for (duplicate, original) in &duplicates {
+        println!(
+            "Work session {} duplicates work session {}",
+            duplicate, original
+        );
+    }
+    if fix {
+        confirm(
+            &format!("Remove {} duplicated work sessions?", duplicates.len()),
+            &mut std::io::stdin().lock(),
+            assume_yes,
+        )?;
+        time_sheet
+            .work_sessions
+            .retain(|work_session| !duplicates.iter().any(|(d, _)| *d == work_session.id));
+        println!("Removed {} duplicated work sessions", duplicates.len());
+        time_sheet.save(time_sheet_path)?;
+    } else {
+        println!("Use --fix to remove them.");
+    }
+    Ok(())
+}
+
+/// Stop a specific running work session, e.g. one forgotten in the past
+pub fn finalize_work_session(
+    id: usize,
+    stop: &str,
+    time_sheet_path: &Path,
+) -> Result<(), TimetrackerError> {
+    let mut time_sheet = TimeSheet::load(time_sheet_path)?;
+    let stop = parse_datetime(stop)?;
+    time_sheet.finalize_work_session(id, stop)?;
+    println!(
+        "Stopped work session {} at {}",
+        id,
+        stop.format(time_sheet.datetime_format())
+    );
+    time_sheet.save(time_sheet_path)?;
+    Ok(())
+}
+
+/// Set the homeoffice flag of every work session started between `from` and `to`, both inclusive
+pub fn set_homeoffice_between(
+    from: &str,
+    to: &str,
+    homeoffice: bool,
+    assume_yes: bool,
+    time_sheet_path: &Path,
+) -> Result<(), TimetrackerError> {
+    let from = parse_date(from)?;
+    let to = parse_date(to)?;
+    if to < from {
+        return Err(TimetrackerError::TimeSheet(String::from(
+            "End of the period lies before its start!",
+        )));
+    }
+    let mut time_sheet = TimeSheet::load(time_sheet_path)?;
+    let mut updated = 0;
+    for work_session in &mut time_sheet.work_sessions {
+        let date = work_session.start.naive_local().date();
+        if from <= date && date <= to && work_session.homeoffice != homeoffice {
+            work_session.homeoffice = homeoffice;
+            updated += 1;
+        }
+    }
+    if updated > 0 {
+        confirm(
+            &format!(
+                "{} {} work sessions between {} and {} as homeoffice?",
+                if homeoffice { "Mark" } else { "Unmark" },
+                updated,
+                from.format(DATE_FORMAT),
+                to.format(DATE_FORMAT)
+            ),
+            &mut std::io::stdin().lock(),
+            assume_yes,
+        )?;
+    }
+    println!(
+        "{} {} work sessions between {} and {} as homeoffice",
+        if homeoffice { "Marked" } else { "Unmarked" },
+        updated,
+        from.format(DATE_FORMAT),
+        to.format(DATE_FORMAT)
+    );
+    time_sheet.save(time_sheet_path)?;
+    Ok(())
+}
+
+/// Remove a work session from the time sheet
+pub fn remove_work_session(id: usize, time_sheet_path: &Path) -> Result<(), TimetrackerError> {
+    let mut time_sheet = TimeSheet::load(time_sheet_path)?;
+    let position = time_sheet.work_session_position(id)?;
+    let work_session = time_sheet.work_sessions.remove(position);
+    println!(
+        "Removed work session {} starting at {}",
+        id,
+        work_session.start.format(time_sheet.datetime_format())
+    );
+    time_sheet.save(time_sheet_path)?;
+    Ok(())
+}
+
+/// Merge two work sessions following each other into a single one.
+///
+/// The first session has to stop exactly when the second one starts and both have to agree on
+/// whether they are billable and invoiced. Descriptions are joined, tags are combined and the
+/// merged session counts as homeoffice if any of both did.
+pub fn merge_sessions(
+    id_a: usize,
+    id_b: usize,
+    time_sheet_path: &Path,
+) -> Result<(), TimetrackerError> {
+    let mut time_sheet = TimeSheet::load(time_sheet_path)?;
+    let position_a = time_sheet.work_session_position(id_a)?;
+    let position_b = time_sheet.work_session_position(id_b)?;
+    if id_a == id_b {
+        return Err(TimetrackerError::TimeSheet(String::from(
+            "Can't merge a work session with itself!",
+        )));
+    }
+    let (first_position, second_position) = if position_a < position_b {
+        (position_a, position_b)
+    } else {
+        (position_b, position_a)
+    };
+    let second = time_sheet.work_sessions.remove(second_position);
+    let first = &mut time_sheet.work_sessions[first_position];
+    let (first_id, second_id) = (first.id, second.id);
+    if first.stop != Some(second.start) {
+        return Err(TimetrackerError::TimeSheet(format!(
+            "Work sessions {} and {} are not adjacent!",
+            first_id, second_id
+        )));
+    }
+    first.activity = match (first.activity, second.activity) {
+        (Some(a), Some(b)) if a != b => {
+            return Err(TimetrackerError::TimeSheet(format!(
+                "Work sessions {} and {} belong to different activities!",
+                first_id, second_id
+            )));
+        }
+        (a, b) => a.or(b),
+    };
+    first.subproject = match (first.subproject, second.subproject) {
+        (Some(a), Some(b)) if a != b => {
+            return Err(TimetrackerError::TimeSheet(format!(
+                "Work sessions {} and {} belong to different subprojects!",
+                first_id, second_id
+            )));
+        }
+        (a, b) => a.or(b),
+    };
+    if first.billable != second.billable {
+        return Err(TimetrackerError::TimeSheet(format!(
+            "Work sessions {} and {} differ in being billable!",
+            first_id, second_id
+        )));
+    }
+    if first.invoiced != second.invoiced {
+        return Err(TimetrackerError::TimeSheet(format!(
+            "Only one of work sessions {} and {} is already invoiced!",
+            first_id, second_id
+        )));
+    }
+    first.stop = second.stop;
+    first.homeoffice |= second.homeoffice;
+    first.fixed_fee = match (first.fixed_fee, second.fixed_fee) {
+        (Some(a), Some(b)) => Some(a + b),
+        (a, b) => a.or(b),
+    };
+    for tag in second.tags {
+        if !first.tags.contains(&tag) {
+            first.tags.push(tag);
+        }
+    }
+    if first.description.is_empty() {
+        first.description = second.description;
+    } else if !second.description.is_empty() {
+        first.description = format!("{}; {}", first.description, second.description);
+    }
+    println!("Merged work sessions {} and {}", first_id, second_id);
+    time_sheet.work_sessions.sort();
+    time_sheet.save(time_sheet_path)?;
+    Ok(())
+}
+
+/// Split a closed work session into two at the given time.
+///
+/// Both parts keep all properties of the original session, only a fixed fee stays with the first
+/// part so it isn't billed twice.
+pub fn split_session(id: usize, at: &str, time_sheet_path: &Path) -> Result<(), TimetrackerError> {
+    let split_time = parse_datetime(at)?;
+    let mut time_sheet = TimeSheet::load(time_sheet_path)?;
+    let datetime_format = time_sheet.datetime_format().to_string();
+    let second_id = time_sheet.new_session_id();
+    let work_session = time_sheet.get_work_session_mut(id)?;
+    let stop = match work_session.stop {
+        Some(s) => s,
+        None => {
+            return Err(TimetrackerError::TimeSheet(String::from(
+                "Can't split a work session that is still running!",
+            )));
+        }
+    };
+    if split_time <= work_session.start || split_time >= stop {
+        return Err(TimetrackerError::TimeSheet(format!(
+            "{} doesn't lie within work session {}!",
+            split_time.format(&datetime_format),
+            id
+        )));
+    }
+    let mut second = work_session.clone();
+    second.id = second_id;
+    second.start = split_time;
+    second.fixed_fee = None;
+    work_session.stop = Some(split_time);
+    time_sheet.work_sessions.push(second);
+    time_sheet.work_sessions.sort();
+    println!(
+        "Split work session {} at {}",
+        id,
+        split_time.format(&datetime_format)
+    );
+    time_sheet.save(time_sheet_path)?;
+    Ok(())
+}
+
+pub fn add_subproject(
+    name: &str,
+    description: &str,
+    parent: Option<usize>,
+    hourly_rate: Option<f32>,
+    time_sheet_path: &Path,
+) -> Result<(), TimetrackerError> {
+    //! Add a new subproject to the time sheet
+
+    let mut time_sheet = TimeSheet::load(time_sheet_path)?;
+    let name = time_sheet.check_subproject_name(name, None)?;
+    if let Some(r) = hourly_rate {
+        check_rate(r)?;
+    }
+    println!("{} | {}", name, description);
+    let id = time_sheet.subprojects.len();
+    let mut subproject = SubProject::new(id, name, description.to_string());
+    subproject.hourly_rate = hourly_rate;
+    time_sheet.subprojects.push(subproject);
+    time_sheet.set_subproject_parent(id, parent)?;
+    time_sheet.save(time_sheet_path)?;
+    Ok(())
+}
+
+/// Change the parent of a subproject, with `None` making it a top level subproject again
+pub fn set_subproject_parent(
+    id: usize,
+    parent: Option<usize>,
+    time_sheet_path: &Path,
+) -> Result<(), TimetrackerError> {
+    let mut time_sheet = TimeSheet::load(time_sheet_path)?;
+    time_sheet.set_subproject_parent(id, parent)?;
+    time_sheet.save(time_sheet_path)?;
+    Ok(())
+}
+
+/// Give a subproject a new name, which has to be unique within the project
+pub fn rename_subproject(
+    id: usize,
+    name: &str,
+    time_sheet_path: &Path,
+) -> Result<(), TimetrackerError> {
+    let mut time_sheet = TimeSheet::load(time_sheet_path)?;
+    time_sheet.check_subproject(id)?;
+    let name = time_sheet.check_subproject_name(name, Some(id))?;
+    if let Some(subproject) = time_sheet.subprojects.iter_mut().find(|s| s.id == id) {
+        subproject.name = name;
+    }
+    time_sheet.save(time_sheet_path)?;
+    Ok(())
+}
+
+/// Set the hourly rate of a subproject, with 0 falling back to the parent's or project's rate again
+pub fn set_subproject_rate(
+    id: usize,
+    hourly_rate: f32,
+    time_sheet_path: &Path,
+) -> Result<(), TimetrackerError> {
+    check_rate(hourly_rate)?;
+    let mut time_sheet = TimeSheet::load(time_sheet_path)?;
+    time_sheet.check_subproject(id)?;
+    let options = AnalyzeOptions::default().with_defaults(&time_sheet);
+    if let Some(subproject) = time_sheet.subprojects.iter_mut().find(|s| s.id == id) {
+        if hourly_rate == 0. {
+            println!("Removing the hourly rate of subproject {}", subproject.name);
+            subproject.hourly_rate = None;
+        } else {
+            println!(
+                "Setting the hourly rate of subproject {} to {}",
+                subproject.name,
+                options.format_money(hourly_rate)
+            );
+            subproject.hourly_rate = Some(hourly_rate);
+        }
+    }
+    time_sheet.save(time_sheet_path)?;
+    Ok(())
+}
+
+/// Repair the subproject IDs of a time sheet by numbering them 0 to n in their current order
+pub fn renumber_subprojects(time_sheet_path: &Path) -> Result<(), TimetrackerError> {
+    let mut time_sheet = TimeSheet::load(time_sheet_path)?;
+    let changes = time_sheet.renumber_subprojects();
+    if changes.is_empty() {
+        println!("All subproject IDs are fine, nothing changed.");
+        return Ok(());
+    }
+    for change in &changes {
+        println!("{}", change);
+    }
+    time_sheet.save(time_sheet_path)?;
+    Ok(())
+}
+
+/// Add a new activity to the time sheet
+pub fn add_activity(
+    name: &str,
+    description: &str,
+    time_sheet_path: &Path,
+) -> Result<(), TimetrackerError> {
+    let mut time_sheet = TimeSheet::load(time_sheet_path)?;
+    let id = time_sheet
+        .activities
+        .iter()
+        .map(|a| a.id + 1)
+        .max()
+        .unwrap_or(0);
+    println!("{} | {} | {}", id, name, description);
+    time_sheet
+        .activities
+        .push(Activity::new(id, name.to_string(), description.to_string()));
+    time_sheet.save(time_sheet_path)?;
+    Ok(())
+}
+
+/// Remove an activity from the time sheet
+pub fn remove_activity(id: usize, time_sheet_path: &Path) -> Result<(), TimetrackerError> {
+    let mut time_sheet = TimeSheet::load(time_sheet_path)?;
+    time_sheet.get_activity_mut(id)?;
+    if time_sheet
+        .work_sessions
+        .iter()
+        .any(|work_session| work_session.activity == Some(id))
+    {
+        return Err(TimetrackerError::Activity(format!(
+            "Activity {} is still assigned to work sessions!",
+            id
+        )));
+    }
+    let activity = time_sheet.get_activity_mut(id)?;
+    println!("Removing activity {} | {}", activity.id, activity.name);
+    time_sheet.activities.retain(|a| a.id != id);
+    time_sheet.save(time_sheet_path)?;
+    Ok(())
+}
+
+/// Change name and/or description of an activity
+pub fn edit_activity(
+    id: usize,
+    name: Option<&str>,
+    description: Option<&str>,
+    time_sheet_path: &Path,
+) -> Result<(), TimetrackerError> {
+    let mut time_sheet = TimeSheet::load(time_sheet_path)?;
+    let activity = time_sheet.get_activity_mut(id)?;
+    if let Some(n) = name {
+        activity.name = n.to_string();
+    }
+    if let Some(d) = description {
+        activity.description = d.to_string();
+    }
+    time_sheet.save(time_sheet_path)?;
+    Ok(())
+}
+
+pub fn list_activities(time_sheet_path: &Path) -> Result<(), TimetrackerError> {
+    let time_sheet = TimeSheet::load(time_sheet_path)?;
+    let mut table = Table::new();
+    table.set_format(*format::consts::FORMAT_NO_BORDER_LINE_SEPARATOR);
+    table.set_titles(row!["ID", "Name", "Description"]);
+    for activity in &time_sheet.activities {
+        table.add_row(row![r->activity.id, activity.name, activity.description]);
+    }
+    table.printstd();
+    Ok(())
+}
+
+/// Escape a text value for use in an iCalendar property.
+fn escape_ical_text(text: &str) -> String {
+    text.replace('\\', "\\\\")
+        .replace(';', "\\;")
+        .replace(',', "\\,")
+        .replace('\n', "\\n")
+}
+
+/// Render all closed work sessions of a time sheet as iCalendar events.
+fn ical_string(time_sheet: &TimeSheet, now: DateTime<Local>) -> String {
+    let ical_format = "%Y%m%dT%H%M%SZ";
+    let mut lines = vec![
+        String::from("BEGIN:VCALENDAR"),
+        String::from("VERSION:2.0"),
+        format!(
+            "PRODID:-//{}//{}//EN",
+            env!("CARGO_PKG_NAME"),
+            env!("CARGO_PKG_VERSION")
+        ),
+    ];
+    for work_session in &time_sheet.work_sessions {
+        let stop = match work_session.stop {
+            Some(s) => s,
+            None => continue,
+        };
+        let start = work_session.start.with_timezone(&Utc);
+        lines.push(String::from("BEGIN:VEVENT"));
+        lines.push(format!(
+            "UID:{}-{}@{}",
+            start.format(ical_format),
+            stop.with_timezone(&Utc).format(ical_format),
+            env!("CARGO_PKG_NAME")
+        ));
+        lines.push(format!(
+            "DTSTAMP:{}",
+            now.with_timezone(&Utc).format(ical_format)
+        ));
+        lines.push(format!("DTSTART:{}", start.format(ical_format)));
+        lines.push(format!(
+            "DTEND:{}",
+            stop.with_timezone(&Utc).format(ical_format)
+        ));
+        lines.push(format!(
+            "SUMMARY:{}",
+            escape_ical_text(&work_session.description)
+        ));
+        lines.push(String::from("END:VEVENT"));
+    }
+    lines.push(String::from("END:VCALENDAR"));
+    let mut ical = lines.join("\r\n");
+    ical.push_str("\r\n");
+    ical
+}
+
+/// Export all closed work sessions as iCalendar file, or to stdout without a `path`. Running work
+/// sessions are skipped.
+pub fn export_ical(path: Option<&Path>, time_sheet_path: &Path) -> Result<(), TimetrackerError> {
+    let time_sheet = TimeSheet::load(time_sheet_path)?;
+    write_export(ical_string(&time_sheet, Local::now()).as_bytes(), path)
+}
+
+/// A work session as written by `export --format flat-json`.
+///
+/// This shape is meant for other tools and stays stable independent of the time sheet layout.
+#[derive(Serialize, Debug)]
+struct FlatWorkSession<'a> {
+    start: DateTime<Local>,
+    stop: Option<DateTime<Local>>,
+    hours: f32,
+    description: &'a str,
+    homeoffice: bool,
+}
+
+/// Export all work sessions as a flat JSON array with ISO 8601 timestamps.
+///
+/// Running work sessions have no stop time and count their hours up to now.
+pub fn export_flat_json(
+    path: Option<&Path>,
+    time_sheet_path: &Path,
+) -> Result<(), TimetrackerError> {
+    let time_sheet = TimeSheet::load(time_sheet_path)?;
+    let now = Local::now();
+    let work_sessions: Vec<FlatWorkSession> = time_sheet
+        .work_sessions
+        .iter()
+        .map(|work_session| FlatWorkSession {
+            start: work_session.start,
+            stop: work_session.stop,
+            hours: hours(work_session.duration(now)),
+            description: &work_session.description,
+            homeoffice: work_session.homeoffice,
+        })
+        .collect();
+    write_export(&serde_json::to_vec_pretty(&work_sessions)?, path)
+}
+
+/// Work sessions split into titled sections for exports.
+///
+/// Without grouping, all work sessions form a single untitled section. Grouped by subproject,
+/// every subproject with work sessions gets a section in ID order, followed by the unassigned ones.
+fn export_sections(
+    time_sheet: &TimeSheet,
+    group_by_subproject: bool,
+) -> Vec<(String, Vec<&WorkSession>)> {
+    if !group_by_subproject {
+        return vec![(String::new(), time_sheet.work_sessions.iter().collect())];
+    }
+    let mut groups: BTreeMap<usize, Vec<&WorkSession>> = BTreeMap::new();
+    let mut unassigned = Vec::new();
+    for work_session in &time_sheet.work_sessions {
+        match work_session.subproject {
+            Some(id) => groups.entry(id).or_default().push(work_session),
+            None => unassigned.push(work_session),
+        }
+    }
+    let mut sections: Vec<(String, Vec<&WorkSession>)> = groups
+        .into_iter()
+        .map(|(id, work_sessions)| {
+            let name = time_sheet
+                .subprojects
+                .iter()
+                .find(|s| s.id == id)
+                .map(|s| s.name.clone())
+                .unwrap_or_else(|| format!("unknown ({})", id));
+            (name, work_sessions)
+        })
+        .collect();
+    if !unassigned.is_empty() {
+        sections.push((String::from("unassigned"), unassigned));
+    }
+    sections
+}
+
+/// Write export sections as CSV with the columns start, stop, description, homeoffice and hours.
+///
+/// Grouped exports get a leading subproject column and a subtotal row after every section.
+fn write_csv_export<W: Write>(
+    writer: W,
+    sections: &[(String, Vec<&WorkSession>)],
+    grouped: bool,
+    datetime_format: &str,
+    now: DateTime<Local>,
+) -> Result<(), TimetrackerError> {
+    let mut csv_writer = csv::Writer::from_writer(writer);
+    let mut header = vec!["start", "stop", "description", "homeoffice", "hours"];
+    if grouped {
+        header.insert(0, "subproject");
+    }
+    csv_writer.write_record(&header)?;
+    for (title, work_sessions) in sections {
+        let mut subtotal = chrono::Duration::zero();
+        for work_session in work_sessions {
+            let duration = work_session.duration(now);
+            subtotal = subtotal + duration;
+            let mut record = vec![
+                work_session.start.format(datetime_format).to_string(),
+                work_session
+                    .stop
+                    .map(|s| s.format(datetime_format).to_string())
+                    .unwrap_or_default(),
+                work_session.description.clone(),
+                work_session.homeoffice.to_string(),
+                format!("{:.02}", hours(duration)),
+            ];
+            if grouped {
+                record.insert(0, title.clone());
+            }
+            csv_writer.write_record(&record)?;
+        }
+        if grouped {
+            csv_writer.write_record([
+                title.as_str(),
+                "",
+                "",
+                "Subtotal",
+                "",
+                &format!("{:.02}", hours(subtotal)),
+            ])?;
+        }
+    }
+    csv_writer.flush()?;
+    Ok(())
+}
+
+/// Render export sections as Markdown tables, with a heading and subtotal row per section if
+/// grouped.
+fn markdown_export(
+    sections: &[(String, Vec<&WorkSession>)],
+    grouped: bool,
+    datetime_format: &str,
+    now: DateTime<Local>,
+) -> String {
+    let mut lines = Vec::new();
+    for (title, work_sessions) in sections {
+        if grouped {
+            lines.push(format!("## {}", title));
+            lines.push(String::new());
+        }
+        lines.push(String::from(
+            "| Start | Stop | Description | Homeoffice | Hours |",
+        ));
+        lines.push(String::from("|---|---|---|---|--:|"));
+        let mut subtotal = chrono::Duration::zero();
+        for work_session in work_sessions {
+            let duration = work_session.duration(now);
+            subtotal = subtotal + duration;
+            lines.push(format!(
+                "| {} | {} | {} | {} | {:.02} |",
+                work_session.start.format(datetime_format),
+                work_session
+                    .stop
+                    .map(|s| s.format(datetime_format).to_string())
+                    .unwrap_or_default(),
+                work_session.description.replace('|', "\\|"),
+                if work_session.homeoffice { "x" } else { "" },
+                hours(duration)
+            ));
+        }
+        if grouped {
+            lines.push(format!(
+                "| | | **Subtotal** | | **{:.02}** |",
+                hours(subtotal)
+            ));
+        }
+        lines.push(String::new());
+    }
+    lines.join("\n")
+}
+
+/// Export all work sessions as CSV, optionally grouped by subproject with subtotals.
+pub fn export_csv(
+    path: Option<&Path>,
+    group_by_subproject: bool,
+    time_sheet_path: &Path,
+) -> Result<(), TimetrackerError> {
+    let time_sheet = TimeSheet::load(time_sheet_path)?;
+    let sections = export_sections(&time_sheet, group_by_subproject);
+    let mut content = Vec::new();
+    write_csv_export(
+        &mut content,
+        &sections,
+        group_by_subproject,
+        time_sheet.datetime_format(),
+        Local::now(),
+    )?;
+    write_export(&content, path)
+}
+
+/// Export all work sessions as Markdown, optionally grouped by subproject with subtotals.
+pub fn export_markdown(
+    path: Option<&Path>,
+    group_by_subproject: bool,
+    time_sheet_path: &Path,
+) -> Result<(), TimetrackerError> {
+    let time_sheet = TimeSheet::load(time_sheet_path)?;
+    let sections = export_sections(&time_sheet, group_by_subproject);
+    let content = markdown_export(
+        &sections,
+        group_by_subproject,
+        time_sheet.datetime_format(),
+        Local::now(),
+    );
+    write_export(content.as_bytes(), path)
+}
+
+/// Parse a CSV row, with times given in `datetime_format` as exported or as `DATETIME_FORMAT`.
+fn parse_csv_row(
+    record: &csv::StringRecord,
+    datetime_format: &str,
+) -> Result<WorkSession, TimetrackerError> {
+    let parse_time = |s: &str| {
+        Local
+            .datetime_from_str(s, datetime_format)
+            .or_else(|_| parse_datetime(s))
+    };
+    let start = parse_time(record.get(0).unwrap_or("").trim())?;
+    let stop = match record.get(1).map(str::trim) {
+        Some("") | None => None,
+        Some(s) => Some(parse_time(s)?),
+    };
+    if let Some(s) = stop {
+        if s < start {
+            return Err(TimetrackerError::TimeSheet(String::from(
+                "Stop time lies before start time",
+            )));
+        }
+    }
+    let description = record.get(2).unwrap_or("").to_string();
+    let homeoffice = match record.get(3).map(|h| h.trim().to_lowercase()).as_deref() {
+        Some("true") | Some("1") | Some("yes") => true,
+        Some("false") | Some("0") | Some("no") | Some("") | None => false,
+        Some(h) => {
+            return Err(TimetrackerError::TimeSheet(format!(
+                "\"{}\" is not a valid homeoffice value",
+                h
+            )));
+        }
+    };
+    Ok(WorkSession::new(start, stop, description, homeoffice))
+}
+
+/// Read work sessions from CSV data with the columns start, stop, description and homeoffice.
+///
+/// The first row is expected to be a header and is skipped.
+fn read_csv_work_sessions<R: Read>(
+    reader: R,
+    datetime_format: &str,
+) -> Result<Vec<WorkSession>, TimetrackerError> {
+    let mut csv_reader = csv::ReaderBuilder::new().flexible(true).from_reader(reader);
+    let mut work_sessions = Vec::new();
+    for record in csv_reader.records() {
+        let record = record?;
+        let line = record.position().map(|p| p.line()).unwrap_or(0);
+        let work_session = parse_csv_row(&record, datetime_format)
+            .map_err(|e| TimetrackerError::Csv(format!("Malformed row in line {}: {}", line, e)))?;
+        work_sessions.push(work_session);
+    }
+    Ok(work_sessions)
+}
+
+/// Import work sessions from a CSV file into the time sheet.
+pub fn import_csv(
+    path: &Path,
+    assume_yes: bool,
+    time_sheet_path: &Path,
+) -> Result<(), TimetrackerError> {
+    let mut time_sheet = TimeSheet::load(time_sheet_path)?;
+    let file = std::fs::File::open(path)?;
+    let work_sessions = read_csv_work_sessions(BufReader::new(file), time_sheet.datetime_format())?;
+    let count = work_sessions.len();
+
+    for mut work_session in work_sessions {
+        work_session.id = time_sheet.new_session_id();
+        time_sheet.work_sessions.push(work_session);
+    }
+    time_sheet.work_sessions.sort();
+    time_sheet.check_overlaps()?;
+    confirm(
+        &format!("Import {} work sessions from {}?", count, path.display()),
+        &mut std::io::stdin().lock(),
+        assume_yes,
+    )?;
+    time_sheet.save(time_sheet_path)?;
+    println!("Imported {} work sessions from {}", count, path.display());
+    Ok(())
+}
+
+/// Outcome of merging one time sheet into another
+#[derive(Default, Debug)]
+struct MergeSummary {
+    added: usize,
+    duplicates: usize,
+    warnings: Vec<String>,
+}
+
+/// Merge the work sessions, subprojects and activities of another time sheet into this one.
+pub fn merge_time_sheets(
+    other: &Path,
+    assume_yes: bool,
+    time_sheet_path: &Path,
+) -> Result<(), TimetrackerError> {
+    let other_time_sheet = TimeSheet::load(other)?;
+    let mut time_sheet = TimeSheet::load(time_sheet_path)?;
+    let summary = time_sheet.merge(other_time_sheet);
+    for warning in &summary.warnings {
+        eprintln!("Warning: skipping work session: {}", warning);
+    }
+    if summary.added > 0 {
+        confirm(
+            &format!(
+                "Merge {} work sessions from {}?",
+                summary.added,
+                other.display()
+            ),
+            &mut std::io::stdin().lock(),
+            assume_yes,
+        )?;
+    }
+    time_sheet.save(time_sheet_path)?;
+    println!(
+        "Merged {} work sessions from {}, skipped {} duplicates and {} overlapping work sessions",
+        summary.added,
+        other.display(),
+        summary.duplicates,
+        summary.warnings.len()
+    );
+    Ok(())
+}
+
+fn parse_date(date: &str) -> Result<NaiveDate, TimetrackerError> {
+    Ok(NaiveDate::parse_from_str(date, DATE_FORMAT)?)
+}
+
+/// The same day `months` months earlier, or the last day of that month if it is shorter.
+fn months_before(date: NaiveDate, months: u32) -> Option<NaiveDate> {
+    let total_months = date
+        .year()
+        .checked_mul(12)?
+        .checked_add(date.month0() as i32)?
+        .checked_sub(i32::try_from(months).ok()?)?;
+    let year = total_months.div_euclid(12);
+    let month = total_months.rem_euclid(12) as u32 + 1;
+    (1..=date.day())
+        .rev()
+        .find_map(|day| NaiveDate::from_ymd_opt(year, month, day))
+}
+
+/// Compute the point in time a relative period like `7d`, `2w` or `1m` before `now`.
+///
+/// Days and weeks are subtracted as fixed durations, months move back in the calendar.
+pub fn parse_since(since: &str, now: DateTime<Local>) -> Result<DateTime<Local>, TimetrackerError> {
+    let invalid = || {
+        TimetrackerError::TimeSheet(format!(
+            "Invalid period \"{}\"! Use a number followed by d, w or m, e.g. 7d.",
+            since
+        ))
+    };
+    let unit = since.chars().last().ok_or_else(invalid)?;
+    let count: u32 = since
+        .strip_suffix(unit)
+        .unwrap_or_default()
+        .parse()
+        .map_err(|_| invalid())?;
+    let period = match unit {
+        'd' => chrono::Duration::days(i64::from(count)),
+        'w' => chrono::Duration::weeks(i64::from(count)),
+        'm' => {
+            let today = now.naive_local().date();
+            today - months_before(today, count).ok_or_else(invalid)?
+        }
+        _ => return Err(invalid()),
+    };
+    now.checked_sub_signed(period).ok_or_else(invalid)
+}
+
+/// Parse a signed offset like `+1h`, `-30m` or `1h30m` into a duration
+pub fn parse_shift(shift: &str) -> Result<chrono::Duration, TimetrackerError> {
+    let invalid = || {
+        TimetrackerError::TimeSheet(format!(
+            "Invalid offset \"{}\"! Use numbers followed by h or m, e.g. +1h or -30m.",
+            shift
+        ))
+    };
+    let (negative, rest) = match shift.strip_prefix('-') {
+        Some(rest) => (true, rest),
+        None => (false, shift.strip_prefix('+').unwrap_or(shift)),
+    };
+    if rest.is_empty() {
+        return Err(invalid());
+    }
+    let mut minutes: i64 = 0;
+    let mut number = String::new();
+    for c in rest.chars() {
+        match c {
+            '0'..='9' => number.push(c),
+            'h' | 'm' => {
+                let count: i64 = number.parse().map_err(|_| invalid())?;
+                number.clear();
+                minutes = count
+                    .checked_mul(if c == 'h' { 60 } else { 1 })
+                    .and_then(|m| minutes.checked_add(m))
+                    .ok_or_else(invalid)?;
+            }
+            _ => return Err(invalid()),
+        }
+    }
+    if !number.is_empty() {
+        return Err(invalid());
+    }
+    if minutes > MAX_SHIFT_HOURS * 60 {
+        return Err(TimetrackerError::TimeSheet(format!(
+            "Offset \"{}\" is too large! Work sessions can be shifted by at most {} hours.",
+            shift, MAX_SHIFT_HOURS
+        )));
+    }
+    let offset = chrono::Duration::minutes(minutes);
+    Ok(if negative { -offset } else { offset })
+}
+
+/// Write the whole time sheet to `out_path` and remove all work sessions from the live sheet,
+/// keeping the project's settings, subprojects and activities.
+pub fn archive(
+    out_path: &Path,
+    assume_yes: bool,
+    time_sheet_path: &Path,
+) -> Result<(), TimetrackerError> {
+    let mut time_sheet = TimeSheet::load(time_sheet_path)?;
+    if time_sheet.work_sessions.iter().any(|w| w.stop.is_none()) {
+        return Err(TimetrackerError::TimeSheet(String::from(
+            "Can't archive while a work session is still running!",
+        )));
+    }
+    if out_path.exists() {
+        return Err(TimetrackerError::TimeSheet(format!(
+            "Archive {} already exists!",
+            out_path.display()
+        )));
+    }
+    confirm(
+        &format!(
+            "Archive {} work sessions to {}?",
+            time_sheet.work_sessions.len(),
+            out_path.display()
+        ),
+        &mut std::io::stdin().lock(),
+        assume_yes,
+    )?;
+    time_sheet.save(out_path)?;
+    println!(
+        "Archived {} work sessions to {}",
+        time_sheet.work_sessions.len(),
+        out_path.display()
+    );
+    time_sheet.work_sessions.clear();
+    time_sheet.save(time_sheet_path)?;
+    Ok(())
+}
+
+/// Change the name of the project
+pub fn rename_project(new_name: &str, time_sheet_path: &Path) -> Result<(), TimetrackerError> {
+    let mut time_sheet = TimeSheet::load(time_sheet_path)?;
+    println!(
+        "Renaming project {} to {}",
+        time_sheet.project_name, new_name
+    );
+    time_sheet.project_name = new_name.to_string();
+    time_sheet.save(time_sheet_path)?;
+    Ok(())
+}
+
+/// Settings `configure_project` changes, leaving every setting that is `None` unchanged.
+///
+/// Numeric limits like the budget or the billing increment are removed again by setting them to 0.
+#[derive(Clone, Default, Debug)]
+pub struct ProjectConfig {
+    /// Hourly rate, replacing the initial rate unless `rate_since` is given
+    pub hourly_rate: Option<f32>,
+    /// Date formatted as `DATE_FORMAT` the new hourly rate takes effect on
+    pub rate_since: Option<String>,
+    pub working_days_per_week: Option<u8>,
+    /// Work sessions shorter than this can't be stopped
+    pub minimum_session_minutes: Option<u32>,
+    /// Total amount the project may cost
+    pub budget: Option<f32>,
+    /// Bill work sessions in multiples of this many minutes
+    pub billing_increment_minutes: Option<u32>,
+    pub rounding_mode: Option<RoundingMode>,
+    /// Warn about running work sessions longer than this
+    pub auto_stop_after_hours: Option<f32>,
+    /// Hours the project is expected to take
+    pub estimated_hours: Option<f32>,
+    /// Whether new work sessions are done in homeoffice unless stated otherwise
+    pub default_homeoffice: Option<bool>,
+    /// Longest allowed working time per day
+    pub max_daily_hours: Option<f32>,
+    /// Shortest allowed rest between two working days
+    pub min_rest_hours: Option<f32>,
+    /// Share of the working time of a day spent in homeoffice above which it is a homeoffice day
+    pub homeoffice_day_threshold: Option<f32>,
+    /// Keep the seconds of start and stop times
+    pub seconds_precision: Option<bool>,
+    /// Flat sum billed for the whole project
+    pub fixed_price: Option<f32>,
+}
+
+/// Change the settings of the project
+pub fn configure_project(
+    config: &ProjectConfig,
+    time_sheet_path: &Path,
+) -> Result<(), TimetrackerError> {
+    let mut time_sheet = TimeSheet::load(time_sheet_path)?;
+    let options = AnalyzeOptions::default().with_defaults(&time_sheet);
+    if let Some(r) = config.hourly_rate {
+        check_rate(r)?;
+        match &config.rate_since {
+            Some(s) => {
+                let since = parse_date(s)?;
+                println!(
+                    "Setting hourly rate to {} from {} on",
+                    options.format_money(r),
+                    since.format(DATE_FORMAT)
+                );
+                time_sheet.change_rate(since, r);
+            }
+            None => {
+                println!("Setting hourly rate to {}", options.format_money(r));
+                time_sheet.change_rate(INITIAL_RATE_DATE, r);
+            }
+        }
+    }
+    if let Some(d) = config.working_days_per_week {
+        if d == 0 || d > 7 {
+            return Err(TimetrackerError::TimeSheet(String::from(
+                "Working days per week must be between 1 and 7!",
+            )));
+        }
+        println!("Setting working days per week to {}", d);
+        time_sheet.working_days_per_week = Some(d);
+    }
+    if let Some(m) = config.minimum_session_minutes {
+        if m == 0 {
+            println!("Removing the minimum work session duration");
+            time_sheet.minimum_session_minutes = None;
+        } else {
+            println!("Setting the minimum work session duration to {} minutes", m);
+            time_sheet.minimum_session_minutes = Some(m);
+        }
+    }
+    if let Some(b) = config.budget {
+        if !b.is_finite() || b < 0. {
+            return Err(TimetrackerError::TimeSheet(format!(
+                "Invalid budget {}! It must be a non-negative number.",
+                b
+            )));
+        }
+        if b == 0. {
+            println!("Removing the budget");
+            time_sheet.budget = None;
+        } else {
+            println!("Setting the budget to {}", options.format_money(b));
+            time_sheet.budget = Some(b);
+        }
+    }
+    if let Some(i) = config.billing_increment_minutes {
+        if i == 0 {
+            println!("Removing the billing increment");
+            time_sheet.billing_increment_minutes = None;
+        } else {
+            println!("Setting the billing increment to {} minutes", i);
+            time_sheet.billing_increment_minutes = Some(i);
+        }
+    }
+    if let Some(mode) = config.rounding_mode {
+        println!("Setting the rounding mode to {:?}", mode);
+        time_sheet.rounding_mode = Some(mode);
+    }
+    if let Some(h) = config.auto_stop_after_hours {
+        if !h.is_finite() || h < 0. {
+            return Err(TimetrackerError::TimeSheet(format!(
+                "Invalid auto stop time {}! It must be a non-negative number of hours.",
+                h
+            )));
+        }
+        if h == 0. {
+            println!("Removing the auto stop time");
+            time_sheet.auto_stop_after_hours = None;
+        } else {
+            println!("Warning about running work sessions after {} hours", h);
+            time_sheet.auto_stop_after_hours = Some(h);
+        }
+    }
+    if let Some(h) = config.estimated_hours {
+        if !h.is_finite() || h < 0. {
+            return Err(TimetrackerError::TimeSheet(format!(
+                "Invalid estimate {}! It must be a non-negative number of hours.",
+                h
+            )));
+        }
+        if h == 0. {
+            println!("Removing the estimate");
+            time_sheet.estimated_hours = None;
+        } else {
+            println!("Setting the estimate to {} hours", h);
+            time_sheet.estimated_hours = Some(h);
+        }
+    }
+    if let Some(h) = config.default_homeoffice {
+        if h {
+            println!("New work sessions are done in homeoffice by default");
+        } else {
+            println!("New work sessions are not done in homeoffice by default");
+        }
+        time_sheet.default_homeoffice = Some(h);
+    }
+    if let Some(h) = config.max_daily_hours {
+        if !h.is_finite() || h <= 0. || h > 24. {
+            return Err(TimetrackerError::TimeSheet(format!(
+                "Invalid maximum daily working time {}! It must be between 0 and 24 hours.",
+                h
+            )));
+        }
+        println!("Setting the maximum daily working time to {} hours", h);
+        time_sheet.max_daily_hours = Some(h);
+    }
+    if let Some(h) = config.min_rest_hours {
+        if !h.is_finite() || h < 0. {
+            return Err(TimetrackerError::TimeSheet(format!(
+                "Invalid minimum rest {}! It must be a non-negative number of hours.",
+                h
+            )));
+        }
+        println!(
+            "Setting the minimum rest between working days to {} hours",
+            h
+        );
+        time_sheet.min_rest_hours = Some(h);
+    }
+    if let Some(t) = config.homeoffice_day_threshold {
+        if !(0. ..1.).contains(&t) {
+            return Err(TimetrackerError::TimeSheet(format!(
+                "Invalid homeoffice day threshold {}! It must be at least 0 and less than 1.",
+                t
+            )));
+        }
+        println!(
+            "Counting days with more than {:.0}% homeoffice as homeoffice days",
+            t * 100.
+        );
+        time_sheet.homeoffice_day_threshold = Some(t);
+    }
+    if let Some(s) = config.seconds_precision {
+        if s {
+            println!("Tracking start and stop times to the second");
+        } else {
+            println!("Tracking start and stop times to the minute");
+        }
+        time_sheet.seconds_precision = Some(s);
+    }
+    if let Some(p) = config.fixed_price {
+        if !p.is_finite() || p < 0. {
+            return Err(TimetrackerError::TimeSheet(format!(
+                "Invalid fixed price {}! It must be a non-negative number.",
+                p
+            )));
+        }
+        if p == 0. {
+            println!("Removing the fixed price, billing by the hour again");
+            time_sheet.fixed_price = None;
+        } else {
+            println!("Setting the fixed price to {}", options.format_money(p));
+            time_sheet.fixed_price = Some(p);
+        }
+    }
+    time_sheet.save(time_sheet_path)?;
+    Ok(())
+}
+
+/// Mark a date as holiday, so it doesn't count as working day in capacity reports
+pub fn add_holiday(date: &str, time_sheet_path: &Path) -> Result<(), TimetrackerError> {
+    let date = parse_date(date)?;
+    let mut time_sheet = TimeSheet::load(time_sheet_path)?;
+    if time_sheet.holidays.contains(&date) {
+        return Err(TimetrackerError::TimeSheet(format!(
+            "{} already is a holiday!",
+            date.format(DATE_FORMAT)
+        )));
+    }
+    time_sheet.holidays.push(date);
+    time_sheet.holidays.sort();
+    time_sheet.save(time_sheet_path)?;
+    Ok(())
+}
+
+/// Remove a date from the holidays
+pub fn remove_holiday(date: &str, time_sheet_path: &Path) -> Result<(), TimetrackerError> {
+    let date = parse_date(date)?;
+    let mut time_sheet = TimeSheet::load(time_sheet_path)?;
+    let length = time_sheet.holidays.len();
+    time_sheet.holidays.retain(|h| *h != date);
+    if time_sheet.holidays.len() == length {
+        return Err(TimetrackerError::TimeSheet(format!(
+            "{} is no holiday!",
+            date.format(DATE_FORMAT)
+        )));
+    }
+    time_sheet.save(time_sheet_path)?;
+    Ok(())
+}
+
+pub fn list_holidays(time_sheet_path: &Path) -> Result<(), TimetrackerError> {
+    let time_sheet = TimeSheet::load(time_sheet_path)?;
+    for holiday in &time_sheet.holidays {
+        println!("{}", holiday.format(DATE_FORMAT));
+    }
+    Ok(())
+}
+
+/// Compare the tracked hours between two dates with the available working hours
+pub fn capacity_report(
+    from: &str,
+    to: &str,
+    time_sheet_path: &Path,
+) -> Result<(), TimetrackerError> {
+    let from = parse_date(from)?;
+    let to = parse_date(to)?;
+    if to < from {
+        return Err(TimetrackerError::TimeSheet(String::from(
+            "End of the period lies before its start!",
+        )));
+    }
+    let time_sheet = TimeSheet::load(time_sheet_path)?;
+    let now = Local::now();
+
+    let tracked_hours = hours(
+        time_sheet
+            .work_sessions
+            .iter()
+            .filter(|work_session| {
+                let date = work_session.start.naive_local().date();
+                from <= date && date <= to
+            })
+            .fold(chrono::Duration::zero(), |total, work_session| {
+                total + work_session.duration(now)
+            }),
+    );
+    let available_hours = time_sheet.available_hours(from, to);
+
+    let mut table = Table::new();
+    table.add_row(row![
+        "Period",
+        format!("{} – {}", from.format(DATE_FORMAT), to.format(DATE_FORMAT))
+    ]);
+    table.add_row(row!["Available hours", r->format!("{:.02}h", available_hours)]);
+    table.add_row(row!["Tracked hours", r->format!("{:.02}h", tracked_hours)]);
+    if available_hours > 0. {
+        table.add_row(row![
+            "Utilization",
+            r->format!("{:.01}%", tracked_hours / available_hours * 100.)
+        ]);
+    }
+    table.printstd();
+    Ok(())
+}
+
+/// Parse a month formatted as `YYYY-MM` into its first day
+pub fn parse_month(month: &str) -> Result<NaiveDate, TimetrackerError> {
+    NaiveDate::parse_from_str(&format!("{}-01", month), DATE_FORMAT).map_err(|_| {
+        TimetrackerError::TimeSheet(format!(
+            "Invalid month \"{}\"! Use the format YYYY-MM, e.g. 2024-03.",
+            month
+        ))
+    })
+}
+
+/// Print every day of a month, the current one by default, with the time tracked on it and the
+/// running total within the month
+pub fn calendar_report(
+    month: Option<&str>,
+    time_sheet_path: &Path,
+) -> Result<(), TimetrackerError> {
+    let now = Local::now();
+    let first = match month {
+        Some(m) => parse_month(m)?,
+        None => now.naive_local().date().with_day(1).unwrap(),
+    };
+    let time_sheet = TimeSheet::load(time_sheet_path)?;
+    let days = time_sheet.daily_durations(now);
+
+    let mut table = Table::new();
+    table.set_format(*format::consts::FORMAT_NO_BORDER_LINE_SEPARATOR);
+    table.set_titles(row!["Date", "Day", "Time [h]", "Cumulative [h]"]);
+    let mut cumulative = chrono::Duration::zero();
+    let mut date = first;
+    while date.month() == first.month() {
+        let duration = days
+            .get(&date)
+            .copied()
+            .unwrap_or_else(chrono::Duration::zero);
+        cumulative = cumulative + duration;
+        table.add_row(row![
+            date.format(DATE_FORMAT),
+            date.format("%a"),
+            r->format!("{:.02}h", hours(duration)),
+            r->format!("{:.02}h", hours(cumulative))
+        ]);
+        date = date.succ();
+    }
+    table.printstd();
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use proptest::prelude::*;
+
+    /*
+    prop_compose! {
+        fn compose_work_sessions(max_length: usize)(work_sessions in any_with::<Vec<WorkSession>>(proptest::collection::size_range(max_length).lift())) -> Vec<WorkSession> {
+            work_sessions
+        }
+    }
+    */
+
+    fn at(s: &str) -> DateTime<Local> {
+        Local.datetime_from_str(s, DATETIME_FORMAT).unwrap()
+    }
+
+    /// A time sheet without hourly rate holding work sessions given by their start and stop
+    fn sheet_with(work_sessions: &[(&str, Option<&str>)]) -> TimeSheet {
+        let mut time_sheet = TimeSheet::new(String::from("test"), None);
+        for (start, stop) in work_sessions {
+            let mut work_session = WorkSession::new(at(start), stop.map(at), String::new(), false);
+            work_session.id = time_sheet.new_session_id();
+            time_sheet.work_sessions.push(work_session);
+        }
+        time_sheet
+    }
+
+    /// A time sheet file in the temporary directory, removed again even if the test fails
+    struct TempPath(PathBuf);
+
+    impl Drop for TempPath {
+        fn drop(&mut self) {
+            let _ = std::fs::remove_file(&self.0);
+        }
+    }
+
+    impl std::ops::Deref for TempPath {
+        type Target = Path;
+
+        fn deref(&self) -> &Path {
+            &self.0
+        }
+    }
+
+    impl AsRef<Path> for TempPath {
+        fn as_ref(&self) -> &Path {
+            &self.0
+        }
+    }
+
+    fn temp_path(name: &str) -> TempPath {
+        TempPath(env::temp_dir().join(format!("timetracker-{}-{}.json", name, std::process::id())))
+    }
+
+    proptest! {
+        #[test]
+        fn test_subproject_creation(id: usize, name in "\\PC*", description in "\\PC*") {
+            let subproject = SubProject::new(id, name.clone(), description.clone());
+            prop_assert_eq!(subproject.id, id);
+            prop_assert_eq!(subproject.name, name);
+            prop_assert_eq!(subproject.description, description);
+        }
+
+        #[test]
+        fn test_subproject_cmp(id_1: usize, id_2: usize, name_1 in "\\PC*", name_2 in "\\PC*", description_1 in "\\PC*", description_2 in "\\PC*") {
+            let subproject_1 = SubProject::new(id_1, name_1, description_1);
+            let subproject_2 = SubProject::new(id_2, name_2, description_2);
+            prop_assert!((id_1 < id_2) == (subproject_1 < subproject_2));
+            prop_assert!((id_1 > id_2) == (subproject_1 > subproject_2));
+            prop_assert!((id_1 == id_2) == (subproject_1 == subproject_2));
+        }
+
+        #[test]
+        fn test_split_description_line_length(description in "\\PC*", max_line_length in 1..100usize) {
+            let split_description = split_description_string(&description, max_line_length);
+            for line in split_description.split('\n') {
+                prop_assert!(line.graphemes(true).count() <= max_line_length);
+            }
+        }
+
+        #[test]
+        fn test_split_description_keeps_content(description in "\\PC*", max_line_length in 0..100usize) {
+            let split_description = split_description_string(&description, max_line_length);
+            prop_assert_eq!(
+                split_description.replace(&['\n', ' '][..], ""),
+                description.replace(' ', "")
+            );
+        }
+
+        #[test]
+        fn test_time_sheet_creation(project_name in "\\PC*", hourly_rate: f32) {
+            let time_sheet = TimeSheet::new(project_name.clone(), Some(hourly_rate));
+            prop_assert_eq!(time_sheet.hourly_rate(), Some(hourly_rate));
+            prop_assert_eq!(time_sheet.project_name, project_name);
+            assert_eq!(time_sheet.work_sessions.len(), 0);
+        }
+    }
+
+    #[test]
+    fn test_add_work_session_keeps_order() {
+        let mut time_sheet = TimeSheet::new(String::from("test"), None);
+        let late = WorkSession::new(
+            at("2020-01-02 09:00"),
+            Some(at("2020-01-02 10:00")),
+            String::new(),
+            false,
+        );
+        let early = WorkSession::new(
+            at("2020-01-01 09:00"),
+            Some(at("2020-01-01 10:00")),
+            String::new(),
+            false,
+        );
+        let overlapping = WorkSession::new(at("2020-01-01 09:30"), None, String::new(), false);
+        time_sheet.add_work_session(late).unwrap();
+        time_sheet.add_work_session(early).unwrap();
+        assert!(time_sheet.add_work_session(overlapping).is_err());
+        assert_eq!(time_sheet.work_sessions().len(), 2);
+        assert_eq!(
+            time_sheet.work_sessions()[0].start(),
+            at("2020-01-01 09:00")
+        );
+        assert_eq!(time_sheet.work_sessions()[0].id(), 1);
+        assert_eq!(time_sheet.work_session_position(0).unwrap(), 1);
+        assert!(time_sheet.work_session_position(2).is_err());
+    }
+
+    #[test]
+    fn test_work_session_duration() {
+        let closed = WorkSession::new(
+            at("2020-01-01 09:00"),
+            Some(at("2020-01-01 10:30")),
+            String::new(),
+            false,
+        );
+        let open =
+            WorkSession::start_new_work_session(at("2020-01-01 09:00"), String::new(), false);
+        let now = at("2020-01-01 11:00");
+        assert_eq!(closed.duration(now), chrono::Duration::minutes(90));
+        assert_eq!(open.duration(now), chrono::Duration::minutes(120));
+    }
+
+    #[test]
+    fn test_total_duration_and_cost() {
+        assert_eq!(sheet_with(&[]).total_duration(), chrono::Duration::zero());
+        let mut time_sheet = sheet_with(&[
+            ("2020-01-01 09:00", Some("2020-01-01 10:30")),
+            ("2020-01-02 09:00", Some("2020-01-02 09:45")),
+        ]);
+        time_sheet.change_rate(INITIAL_RATE_DATE, 10.);
+        assert_eq!(time_sheet.total_duration(), chrono::Duration::minutes(135));
+        assert_eq!(time_sheet.total_cost(), Some(22.5));
+        time_sheet.work_sessions[1].billable = false;
+        assert_eq!(time_sheet.total_duration(), chrono::Duration::minutes(135));
+        assert_eq!(time_sheet.total_cost(), Some(15.));
+        time_sheet.work_sessions[0].fixed_fee = Some(100.);
+        assert_eq!(time_sheet.total_cost(), Some(115.));
+        time_sheet.rate_changes.clear();
+        assert_eq!(time_sheet.total_cost(), Some(100.));
+        time_sheet.work_sessions[0].fixed_fee = None;
+        assert_eq!(time_sheet.total_cost(), None);
+    }
+
+    #[test]
+    fn test_rate_changes() {
+        let date = |s: &str| NaiveDate::parse_from_str(s, DATE_FORMAT).unwrap();
+        let mut time_sheet = sheet_with(&[
+            ("2020-01-31 09:00", Some("2020-01-31 10:00")),
+            ("2020-02-01 09:00", Some("2020-02-01 10:00")),
+        ]);
+        time_sheet.change_rate(INITIAL_RATE_DATE, 10.);
+        time_sheet.change_rate(date("2020-02-01"), 20.);
+        assert_eq!(time_sheet.rate_at(date("2020-01-31")), Some(10.));
+        assert_eq!(time_sheet.rate_at(date("2020-02-01")), Some(20.));
+        assert_eq!(time_sheet.total_cost(), Some(30.));
+        time_sheet.change_rate(date("2020-01-01"), 15.);
+        time_sheet.change_rate(date("2020-02-01"), 25.);
+        assert_eq!(time_sheet.rate_changes.len(), 3);
+        assert_eq!(time_sheet.total_cost(), Some(40.));
+    }
+
+    #[test]
+    fn test_migrate_hourly_rate() {
+        let path = temp_path("migrate-rate");
+        std::fs::write(
+            &path,
+            r#"{
+                "project_name": "test",
+                "hourly_rate": 10.0,
+                "work_sessions": [],
+                "rate_changes": [["2020-02-01", 20.0]],
+                "schema_version": 2
+            }"#,
+        )
+        .unwrap();
+        let date = |s: &str| NaiveDate::parse_from_str(s, DATE_FORMAT).unwrap();
+        let time_sheet = TimeSheet::load(&path).unwrap();
+        assert_eq!(time_sheet.schema_version, SCHEMA_VERSION);
+        assert_eq!(time_sheet.hourly_rate, None);
+        assert_eq!(
+            time_sheet.rate_changes,
+            vec![(INITIAL_RATE_DATE, 10.), (date("2020-02-01"), 20.)]
+        );
+        assert_eq!(time_sheet.rate_at(date("2020-01-31")), Some(10.));
+        time_sheet.save(&path).unwrap();
+        assert_eq!(TimeSheet::load(&path).unwrap(), time_sheet);
+    }
+
+    #[test]
+    fn test_subproject_rates() {
+        let mut time_sheet = sheet_with(&[
+            ("2020-01-01 09:00", Some("2020-01-01 10:00")),
+            ("2020-01-02 09:00", Some("2020-01-02 10:00")),
+            ("2020-01-03 09:00", Some("2020-01-03 10:00")),
+            ("2020-01-04 09:00", Some("2020-01-04 10:00")),
+        ]);
+        time_sheet.change_rate(INITIAL_RATE_DATE, 10.);
+        let mut parent = SubProject::new(0, String::from("parent"), String::new());
+        parent.hourly_rate = Some(50.);
+        let mut child = SubProject::new(1, String::from("child"), String::new());
+        child.parent = Some(0);
+        time_sheet.subprojects.push(parent);
+        time_sheet.subprojects.push(child);
+        time_sheet
+            .subprojects
+            .push(SubProject::new(2, String::from("plain"), String::new()));
+        for (work_session, subproject) in
+            time_sheet
+                .work_sessions
+                .iter_mut()
+                .zip(&[None, Some(0), Some(1), Some(2)])
+        {
+            work_session.subproject = *subproject;
+        }
+        assert_eq!(time_sheet.total_cost(), Some(10. + 50. + 50. + 10.));
+    }
+
+    #[test]
+    fn test_homeoffice_days_across_midnight() {
+        let mut time_sheet = sheet_with(&[
+            ("2019-12-31 23:00", Some("2020-01-01 01:00")),
+            ("2020-01-01 23:00", Some("2020-01-02 00:00")),
+        ]);
+        for work_session in time_sheet.work_sessions.iter_mut() {
+            work_session.homeoffice = true;
+        }
+        let homeoffice_days = time_sheet.homeoffice_days(Local::now());
+        assert_eq!(homeoffice_days["2019"].len(), 1);
+        assert_eq!(homeoffice_days["2020"].len(), 1);
+        let homeoffice_durations = time_sheet.homeoffice_durations(Local::now());
+        assert_eq!(homeoffice_durations["2019"], chrono::Duration::hours(2));
+        assert_eq!(homeoffice_durations["2020"], chrono::Duration::hours(1));
+    }
+
+    #[test]
+    fn test_homeoffice_days_mixed() {
+        let mut time_sheet = sheet_with(&[
+            ("2020-01-01 08:00", Some("2020-01-01 11:00")),
+            ("2020-01-01 12:00", Some("2020-01-01 17:00")),
+            ("2020-01-02 08:00", Some("2020-01-02 13:00")),
+            ("2020-01-02 14:00", Some("2020-01-02 17:00")),
+            ("2020-01-03 08:00", Some("2020-01-03 12:00")),
+            ("2020-01-03 13:00", Some("2020-01-03 17:00")),
+        ]);
+        // Every morning is spent in homeoffice
+        for work_session in time_sheet.work_sessions.iter_mut().step_by(2) {
+            work_session.homeoffice = true;
+        }
+        let days = time_sheet.homeoffice_days(Local::now());
+        assert_eq!(
+            days["2020"].iter().collect::<Vec<_>>(),
+            vec![&at("2020-01-02 00:00").date()]
+        );
+        time_sheet.homeoffice_day_threshold = Some(0.3);
+        assert_eq!(time_sheet.homeoffice_days(Local::now())["2020"].len(), 3);
+    }
+
+    #[test]
+    fn test_filter_closed_only() {
+        let mut time_sheet = sheet_with(&[
+            ("2020-01-01 09:00", Some("2020-01-01 10:00")),
+            ("2020-01-01 11:00", None),
+        ]);
+        let options = AnalyzeOptions {
+            closed_only: true,
+            ..Default::default()
+        };
+        let now = Local::now();
+        assert_eq!(
+            time_sheet.filter_work_sessions(|work_session| options.includes(work_session, now)),
+            vec![0]
+        );
+        assert_eq!(time_sheet.work_sessions.len(), 1);
+        assert_eq!(time_sheet.total_duration(), chrono::Duration::hours(1));
+    }
+
+    #[test]
+    fn test_filter_tags() {
+        let tagged = |tags: &[&str]| {
+            let mut work_session = WorkSession::new(Local::now(), None, String::new(), false);
+            work_session.tags = tags.iter().map(|t| t.to_string()).collect();
+            work_session
+        };
+        let options = AnalyzeOptions {
+            tags: vec![String::from("client")],
+            excluded_tags: vec![String::from("break")],
+            ..Default::default()
+        };
+        let now = Local::now();
+        assert!(options.includes(&tagged(&["client"]), now));
+        assert!(!options.includes(&tagged(&[]), now));
+        assert!(!options.includes(&tagged(&["client", "break"]), now));
+        let options = AnalyzeOptions {
+            excluded_tags: vec![String::from("break")],
+            ..Default::default()
+        };
+        assert!(options.includes(&tagged(&[]), now));
+        assert!(!options.includes(&tagged(&["break"]), now));
+    }
+
+    #[test]
+    fn test_weekday_durations() {
+        // 2020-01-06 is a Monday, 2020-01-12 a Sunday
+        let time_sheet = sheet_with(&[
+            ("2020-01-06 09:00", Some("2020-01-06 10:00")),
+            ("2020-01-12 09:00", Some("2020-01-12 10:30")),
+            ("2020-01-13 09:00", Some("2020-01-13 09:30")),
+        ]);
+        let weekdays = time_sheet.weekday_durations(Local::now());
+        assert_eq!(weekdays[0], chrono::Duration::minutes(90));
+        assert_eq!(weekdays[6], chrono::Duration::minutes(90));
+        assert_eq!(weekdays[3], chrono::Duration::zero());
+    }
+
+    #[test]
+    fn test_parse_since() {
+        let now = at("2020-03-31 12:00");
+        assert_eq!(parse_since("7d", now).unwrap(), at("2020-03-24 12:00"));
+        assert_eq!(parse_since("2w", now).unwrap(), at("2020-03-17 12:00"));
+        assert_eq!(parse_since("1m", now).unwrap(), at("2020-02-29 12:00"));
+        assert_eq!(parse_since("13m", now).unwrap(), at("2019-02-28 12:00"));
+        assert!(parse_since("7", now).is_err());
+        assert!(parse_since("d", now).is_err());
+        assert!(parse_since("7y", now).is_err());
+        assert!(parse_since("7€", now).is_err());
+        assert!(parse_since("", now).is_err());
+        assert!(parse_since("4000000000d", now).is_err());
+        assert!(parse_since("2147483648m", now).is_err());
+        assert!(parse_since("4294967295m", now).is_err());
+    }
+
+    #[test]
+    fn test_gaps() {
+        let time_sheet = sheet_with(&[
+            ("2020-01-01 09:00", Some("2020-01-01 10:00")),
+            ("2020-01-01 10:00", Some("2020-01-01 11:00")),
+            ("2020-01-01 12:00", Some("2020-01-01 13:00")),
+            ("2020-01-02 09:00", Some("2020-01-02 10:00")),
+        ]);
+        assert_eq!(
+            time_sheet.gaps(),
+            vec![(at("2020-01-01 11:00"), at("2020-01-01 12:00"))]
+        );
+    }
+
+    #[test]
+    fn test_sessions_in_range() {
+        let time_sheet = sheet_with(&[
+            ("2020-01-01 08:00", Some("2020-01-01 10:00")),
+            ("2020-01-01 10:00", Some("2020-01-01 10:00")),
+            ("2020-01-01 11:00", Some("2020-01-01 13:00")),
+            ("2020-01-01 13:00", Some("2020-01-01 14:00")),
+            ("2020-01-01 15:00", None),
+        ]);
+        let starts = |from: &str, to: &str| -> Vec<DateTime<Local>> {
+            time_sheet
+                .sessions_in_range(at(from), at(to))
+                .iter()
+                .map(|w| w.start)
+                .collect()
+        };
+        assert_eq!(
+            starts("2020-01-01 10:00", "2020-01-01 13:00"),
+            vec![at("2020-01-01 10:00"), at("2020-01-01 11:00")]
+        );
+        assert_eq!(
+            starts("2020-01-01 12:00", "2020-01-01 12:30"),
+            vec![at("2020-01-01 11:00")]
+        );
+        assert_eq!(
+            starts("2020-01-02 00:00", "2020-01-03 00:00"),
+            vec![at("2020-01-01 15:00")]
+        );
+        assert!(starts("2019-12-31 00:00", "2020-01-01 08:00").is_empty());
+    }
+
+    #[test]
+    fn test_legal_violations() {
+        let mut time_sheet = sheet_with(&[
+            ("2020-01-01 08:00", Some("2020-01-01 14:00")),
+            ("2020-01-01 15:00", Some("2020-01-01 20:30")),
+            ("2020-01-02 06:00", Some("2020-01-02 12:00")),
+            ("2020-01-03 08:00", Some("2020-01-03 18:00")),
+        ]);
+        let violations = time_sheet.legal_violations(Local::now());
+        let found: Vec<(Date<Local>, chrono::Duration)> = violations
+            .iter()
+            .map(|(date, _, duration, _)| (*date, *duration))
+            .collect();
+        assert_eq!(
+            found,
+            vec![
+                (
+                    at("2020-01-01 00:00").date(),
+                    chrono::Duration::minutes(690)
+                ),
+                (
+                    at("2020-01-02 00:00").date(),
+                    chrono::Duration::minutes(570)
+                ),
+            ]
+        );
+
+        time_sheet.max_daily_hours = Some(12.);
+        time_sheet.min_rest_hours = Some(9.);
+        assert!(time_sheet.legal_violations(Local::now()).is_empty());
+    }
+
+    #[test]
+    fn test_available_hours() {
+        let date = |s: &str| NaiveDate::parse_from_str(s, DATE_FORMAT).unwrap();
+        let mut time_sheet = TimeSheet::new(String::from("test"), None);
+        // 2020-01-06 is a Monday
+        assert_eq!(
+            time_sheet.available_hours(date("2020-01-06"), date("2020-01-12")),
+            40.
+        );
+        time_sheet.holidays.push(date("2020-01-06"));
+        assert_eq!(
+            time_sheet.available_hours(date("2020-01-06"), date("2020-01-12")),
+            32.
+        );
+        time_sheet.working_days_per_week = Some(3);
+        assert_eq!(
+            time_sheet.available_hours(date("2020-01-06"), date("2020-01-12")),
+            16.
+        );
+    }
+
+    #[test]
+    fn test_load_missing_time_sheet() {
+        match TimeSheet::load(Path::new("does/not/exist.json")) {
+            Err(TimetrackerError::IOError(e)) => assert!(e.contains("init")),
+            r => panic!("Unexpected result: {:?}", r),
+        }
+    }
+
+    #[test]
+    fn test_initialize_existing_project() {
+        let path = temp_path("init");
+        initialize_project(String::from("first"), None, false, true, &path).unwrap();
+        match initialize_project(String::from("second"), None, false, true, &path) {
+            Err(TimetrackerError::TimeSheet(e)) => assert!(e.contains("--force")),
+            r => panic!("Unexpected result: {:?}", r),
+        }
+        assert_eq!(TimeSheet::load(&path).unwrap().project_name(), "first");
+        initialize_project(String::from("second"), None, true, true, &path).unwrap();
+        assert_eq!(TimeSheet::load(&path).unwrap().project_name(), "second");
+    }
+
+    #[test]
+    fn test_merge_time_sheets() {
+        let session = |start: &str, stop: &str| {
+            WorkSession::new(at(start), Some(at(stop)), String::new(), false)
+        };
+        let mut time_sheet = TimeSheet::new(String::from("test"), None);
+        time_sheet
+            .add_work_session(session("2020-01-01 09:00", "2020-01-01 10:00"))
+            .unwrap();
+        let mut other = TimeSheet::new(String::from("other"), None);
+        other
+            .activities
+            .push(Activity::new(3, String::from("meeting"), String::new()));
+        other
+            .work_sessions
+            .push(session("2020-01-01 09:00", "2020-01-01 10:00"));
+        other
+            .work_sessions
+            .push(session("2020-01-01 09:30", "2020-01-01 10:30"));
+        let mut meeting = session("2020-01-01 08:00", "2020-01-01 09:00");
+        meeting.activity = Some(3);
+        other.work_sessions.push(meeting);
+
+        let summary = time_sheet.merge(other);
+        assert_eq!(summary.added, 1);
+        assert_eq!(summary.duplicates, 1);
+        assert_eq!(summary.warnings.len(), 1);
+        assert_eq!(time_sheet.work_sessions.len(), 2);
+        assert_eq!(time_sheet.work_sessions[0].start, at("2020-01-01 08:00"));
+        assert_eq!(time_sheet.work_sessions[0].activity, Some(0));
+        assert_eq!(time_sheet.activities[0].name, "meeting");
+    }
+
+    #[test]
+    fn test_merge_time_sheets_subprojects() {
+        let mut time_sheet = TimeSheet::new(String::from("test"), None);
+        time_sheet
+            .subprojects
+            .push(SubProject::new(0, String::from("backend"), String::new()));
+        let mut other = TimeSheet::new(String::from("other"), None);
+        let mut child = SubProject::new(0, String::from("api"), String::new());
+        child.parent = Some(1);
+        child.hourly_rate = Some(50.);
+        other.subprojects.push(child);
+        other
+            .subprojects
+            .push(SubProject::new(1, String::from("frontend"), String::new()));
+        let mut work_session = WorkSession::new(
+            at("2020-01-01 09:00"),
+            Some(at("2020-01-01 10:00")),
+            String::new(),
+            false,
+        );
+        work_session.subproject = Some(0);
+        other.work_sessions.push(work_session);
+
+        let summary = time_sheet.merge(other);
+        assert_eq!(summary.added, 1);
+        assert_eq!(time_sheet.subprojects.len(), 3);
+        let api = &time_sheet.subprojects[1];
+        assert_eq!((api.id, api.name.as_str()), (1, "api"));
+        assert_eq!(api.parent, Some(2));
+        assert_eq!(api.hourly_rate, Some(50.));
+        assert_eq!(time_sheet.subprojects[2].name, "frontend");
+        assert_eq!(time_sheet.work_sessions[0].subproject, Some(1));
+    }
+
+    #[test]
+    fn test_invalid_hourly_rate() {
+        let path = temp_path("rate");
+        for rate in &[-5.0, f32::NAN] {
+            match initialize_project(String::from("test"), Some(*rate), true, true, &path) {
+                Err(TimetrackerError::TimeSheet(e)) => assert!(e.contains("hourly rate")),
+                r => panic!("Unexpected result: {:?}", r),
+            }
+        }
+        assert!(!path.exists());
+    }
+
+    #[test]
+    fn test_ical_string() {
+        let mut time_sheet = sheet_with(&[
+            ("2020-01-01 09:00", Some("2020-01-01 10:00")),
+            ("2020-01-02 09:00", None),
+        ]);
+        time_sheet.work_sessions[0].description = String::from("a, b; c");
+        let ical = ical_string(&time_sheet, at("2020-01-02 10:00"));
+        let utc_start = at("2020-01-01 09:00").with_timezone(&Utc);
+        assert_eq!(ical.matches("BEGIN:VEVENT").count(), 1);
+        assert!(ical.contains(&format!("DTSTART:{}", utc_start.format("%Y%m%dT%H%M%SZ"))));
+        assert!(ical.contains("SUMMARY:a\\, b\\; c\r\n"));
+    }
+
+    #[test]
+    fn test_time_sheet_path() {
+        let env_file = Some(std::ffi::OsStr::new("/tmp/sheet.json"));
+        assert_eq!(
+            time_sheet_path(None, None),
+            PathBuf::from("time_sheet.json")
+        );
+        assert_eq!(
+            time_sheet_path(None, Some(std::ffi::OsStr::new(""))),
+            PathBuf::from("time_sheet.json")
+        );
+        assert_eq!(
+            time_sheet_path(None, env_file),
+            PathBuf::from("/tmp/sheet.json")
+        );
+        assert_eq!(
+            time_sheet_path(Some("other.json"), env_file),
+            PathBuf::from("other.json")
+        );
+    }
+
+    #[test]
+    fn test_nested_subprojects() {
+        let mut time_sheet = sheet_with(&[
+            ("2020-01-01 09:00", Some("2020-01-01 10:00")),
+            ("2020-01-01 10:00", Some("2020-01-01 12:00")),
+        ]);
+        time_sheet.change_rate(INITIAL_RATE_DATE, 10.);
+        for (id, name) in ["backend", "api", "docs"].iter().enumerate() {
+            time_sheet
+                .subprojects
+                .push(SubProject::new(id, name.to_string(), String::new()));
+        }
+        time_sheet.set_subproject_parent(1, Some(0)).unwrap();
+        assert!(matches!(
+            time_sheet.set_subproject_parent(2, Some(2)),
+            Err(TimetrackerError::Subproject(_))
+        ));
+        assert!(matches!(
+            time_sheet.set_subproject_parent(0, Some(1)),
+            Err(TimetrackerError::Subproject(_))
+        ));
+        assert!(matches!(
+            time_sheet.set_subproject_parent(2, Some(3)),
+            Err(TimetrackerError::Subproject(_))
+        ));
+        assert_eq!(time_sheet.subprojects[0].parent(), None);
+
+        time_sheet.work_sessions[0].subproject = Some(0);
+        time_sheet.work_sessions[1].subproject = Some(1);
+        let totals = time_sheet.subproject_totals(Local::now());
+        assert_eq!(totals[&0], (chrono::Duration::hours(3), Some(30.)));
+        assert_eq!(totals[&1], (chrono::Duration::hours(2), Some(20.)));
+        assert!(!totals.contains_key(&2));
+    }
+
+    #[test]
+    fn test_finalize_work_session() {
+        let mut time_sheet = sheet_with(&[("2020-01-01 09:00", None), ("2020-01-01 12:00", None)]);
+        assert!(time_sheet
+            .finalize_work_session(0, at("2020-01-01 08:00"))
+            .is_err());
+        assert!(time_sheet
+            .finalize_work_session(0, at("2020-01-01 12:30"))
+            .is_err());
+        time_sheet
+            .finalize_work_session(0, at("2020-01-01 12:00"))
+            .unwrap();
+        assert_eq!(
+            time_sheet.work_sessions[0].stop,
+            Some(at("2020-01-01 12:00"))
+        );
+        assert!(time_sheet
+            .finalize_work_session(0, at("2020-01-01 11:00"))
+            .is_err());
+        time_sheet
+            .finalize_work_session(1, at("2020-01-01 13:00"))
+            .unwrap();
+    }
+
+    #[test]
+    fn test_subproject_name_unique() {
+        let mut time_sheet = TimeSheet::new(String::from("test"), None);
+        time_sheet
+            .subprojects
+            .push(SubProject::new(0, String::from("Backend"), String::new()));
+        assert_eq!(
+            time_sheet.check_subproject_name("  docs ", None).unwrap(),
+            "docs"
+        );
+        assert!(matches!(
+            time_sheet.check_subproject_name(" backend", None),
+            Err(TimetrackerError::Subproject(_))
+        ));
+        assert!(matches!(
+            time_sheet.check_subproject_name("  ", None),
+            Err(TimetrackerError::Subproject(_))
+        ));
+        assert_eq!(
+            time_sheet
+                .check_subproject_name("BACKEND", Some(0))
+                .unwrap(),
+            "BACKEND"
+        );
+    }
+
+    #[test]
+    fn test_toml_round_trip() {
+        let mut time_sheet = TimeSheet::new(String::from("test"), Some(10.));
+        time_sheet.change_rate(NaiveDate::from_ymd(2020, 1, 2), 20.);
+        time_sheet
+            .subprojects
+            .push(SubProject::new(0, String::from("a"), String::new()));
+        time_sheet
+            .add_work_session(WorkSession::new(
+                at("2020-01-01 09:00"),
+                Some(at("2020-01-01 10:00")),
+                String::from("closed"),
+                true,
+            ))
+            .unwrap();
+        time_sheet
+            .add_work_session(WorkSession::new(
+                at("2020-01-02 09:00"),
+                None,
+                String::from("open"),
+                false,
+            ))
+            .unwrap();
+        let toml_string = time_sheet.to_toml().unwrap();
+        let parsed: TimeSheet = toml::from_str(&toml_string).unwrap();
+        assert_eq!(parsed, time_sheet);
+        assert_eq!(parsed.work_sessions[1].stop, None);
+        assert_eq!(parsed.next_session_id, 2);
+    }
+
+    #[test]
+    fn test_rounded_total() {
+        let mut options = AnalyzeOptions::default();
+        assert_eq!(options.rounded_total(12.34), None);
+        options.round_total = Some(1.);
+        assert_eq!(options.rounded_total(12.34), Some(12.));
+        assert_eq!(options.rounded_total(12.5), Some(13.));
+        options.exchange_rate = Some(2.);
+        assert_eq!(options.rounded_total(12.34), Some(12.5));
+    }
+
+    #[test]
+    fn test_format_number_locale() {
+        let mut options = AnalyzeOptions::default();
+        assert_eq!(options.format_number(1234.5), "1234.50");
+        options.locale = Some(Locale::De);
+        assert_eq!(options.format_number(1234.5), "1.234,50");
+        assert_eq!(options.format_number(-1234567.), "-1.234.567,00");
+        assert_eq!(options.format_number(12.), "12,00");
+        assert_eq!(options.format_money(1234.56), "1.234,56 €");
+        options.precision = Some(0);
+        assert_eq!(options.format_number(1234.), "1.234");
+    }
+
+    #[test]
+    fn test_round_duration() {
+        let minutes = chrono::Duration::minutes;
+        for &(mode, seven, eight) in &[
+            (RoundingMode::Up, 15, 15),
+            (RoundingMode::Down, 0, 0),
+            (RoundingMode::Nearest, 0, 15),
+        ] {
+            assert_eq!(round_duration(minutes(7), 15, mode), minutes(seven));
+            assert_eq!(round_duration(minutes(8), 15, mode), minutes(eight));
+            assert_eq!(round_duration(minutes(30), 15, mode), minutes(30));
+        }
+    }
+
+    #[test]
+    fn test_default_homeoffice() {
+        let path = temp_path("remote");
+        initialize_project(String::from("test"), None, true, true, &path).unwrap();
+        let config = ProjectConfig {
+            default_homeoffice: Some(true),
+            ..ProjectConfig::default()
+        };
+        configure_project(&config, &path).unwrap();
+        for (start, homeoffice) in [
+            ("2020-01-01 09:00", None),
+            ("2020-01-02 09:00", Some(false)),
+        ]
+        .iter()
+        {
+            add_work_session_to_time_sheet(
+                None,
+                start,
+                None,
+                None,
+                *homeoffice,
+                None,
+                None,
+                true,
+                None,
+                None,
+                &path,
+            )
+            .unwrap();
+        }
+        let time_sheet = TimeSheet::load(&path).unwrap();
+        let flags: Vec<bool> = time_sheet
+            .work_sessions()
+            .iter()
+            .map(|w| w.homeoffice())
+            .collect();
+        assert_eq!(flags, vec![true, false]);
+    }
+
+    #[test]
+    fn test_create_invoice() {
+        let path = temp_path("invoice");
+        initialize_project(String::from("test"), Some(10.), true, true, &path).unwrap();
+        add_work_session_to_time_sheet(
+            None,
+            "2020-01-01 09:00",
+            Some("2020-01-01 10:00"),
+            None,
+            None,
+            None,
+            None,
+            true,
+            None,
+            None,
+            &path,
+        )
+        .unwrap();
+        assert!(create_invoice(Some("2019-12-31"), &path).is_err());
+        assert_eq!(TimeSheet::load(&path).unwrap().last_invoice_date, None);
+        create_invoice(Some("2020-01-01"), &path).unwrap();
+        assert_eq!(
+            TimeSheet::load(&path).unwrap().last_invoice_date,
+            Some(NaiveDate::from_ymd(2020, 1, 1))
+        );
+        assert!(create_invoice(Some("2020-01-02"), &path).is_err());
+    }
+
+    #[test]
+    fn test_create_invoice_running_work_session() {
+        let path = temp_path("invoice-running");
+        let mut time_sheet = sheet_with(&[
+            ("2020-01-01 09:00", Some("2020-01-01 10:00")),
+            ("2020-01-01 11:00", None),
+        ]);
+        time_sheet.change_rate(INITIAL_RATE_DATE, 10.);
+        time_sheet.save(&path).unwrap();
+        create_invoice(Some("2020-01-01"), &path).unwrap();
+        let invoiced = |path: &Path| -> Vec<bool> {
+            TimeSheet::load(path)
+                .unwrap()
+                .work_sessions
+                .iter()
+                .map(|w| w.invoiced)
+                .collect()
+        };
+        assert_eq!(invoiced(&path), vec![true, false]);
+
+        let mut time_sheet = TimeSheet::load(&path).unwrap();
+        time_sheet.get_work_session_mut(1).unwrap().stop = Some(at("2020-01-01 12:00"));
+        time_sheet.save(&path).unwrap();
+        create_invoice(Some("2020-01-01"), &path).unwrap();
+        assert_eq!(invoiced(&path), vec![true, true]);
+        assert!(create_invoice(Some("2020-01-01"), &path).is_err());
+    }
+
+    #[test]
+    fn test_migrate_invoiced_work_sessions() {
+        let path = temp_path("migrate-invoiced");
+        let mut time_sheet = sheet_with(&[
+            ("2020-01-01 09:00", Some("2020-01-01 10:00")),
+            ("2020-01-02 09:00", Some("2020-01-02 10:00")),
+        ]);
+        time_sheet.schema_version = 1;
+        time_sheet.last_invoice_date = Some(NaiveDate::from_ymd(2020, 1, 1));
+        time_sheet.save(&path).unwrap();
+        let time_sheet = TimeSheet::load(&path).unwrap();
+        assert_eq!(time_sheet.schema_version, SCHEMA_VERSION);
+        let flags: Vec<bool> = time_sheet
+            .work_sessions
+            .iter()
+            .map(|w| w.invoiced)
+            .collect();
+        assert_eq!(flags, vec![true, false]);
+    }
+
+    #[test]
+    fn test_duplicate_work_sessions() {
+        let mut time_sheet = sheet_with(&[
+            ("2020-01-01 09:00", Some("2020-01-01 10:00")),
+            ("2020-01-01 09:00", Some("2020-01-01 10:00")),
+            ("2020-01-01 11:00", Some("2020-01-01 12:00")),
+            ("2020-01-01 09:00", Some("2020-01-01 10:00")),
+        ]);
+        time_sheet.work_sessions[1].homeoffice = true;
+        assert_eq!(time_sheet.duplicate_work_sessions(), vec![(1, 0), (3, 0)]);
+    }
+
+    #[test]
+    fn test_progress_bar() {
+        assert_eq!(progress_bar(0., 10.), "[--------------------] 0%");
+        assert_eq!(progress_bar(5., 10.), "[##########----------] 50%");
+        assert_eq!(progress_bar(15., 10.), "[####################] 150%");
+    }
+
+    #[test]
+    fn test_renumber_subprojects() {
+        let mut time_sheet = TimeSheet::new(String::from("test"), None);
+        for (id, name) in [(3, "a"), (3, "b"), (7, "c")].iter() {
+            time_sheet
+                .subprojects
+                .push(SubProject::new(*id, name.to_string(), String::new()));
+        }
+        time_sheet.subprojects[2].parent = Some(3);
+        for subproject in [Some(7), Some(3), Some(5), None].iter() {
+            let mut work_session = WorkSession::new(Local::now(), None, String::new(), false);
+            work_session.subproject = *subproject;
+            time_sheet.work_sessions.push(work_session);
+        }
+        let changes = time_sheet.renumber_subprojects();
+        assert_eq!(changes.len(), 4);
+        let ids: Vec<usize> = time_sheet.subprojects.iter().map(|s| s.id).collect();
+        assert_eq!(ids, vec![0, 1, 2]);
+        assert_eq!(time_sheet.subprojects[2].parent, Some(0));
+        let references: Vec<Option<usize>> = time_sheet
+            .work_sessions
+            .iter()
+            .map(|w| w.subproject)
+            .collect();
+        assert_eq!(references, vec![Some(2), Some(0), None, None]);
+        assert!(time_sheet.renumber_subprojects().is_empty());
+    }
+
+    #[test]
+    fn test_global_config_precedence() {
+        let mut time_sheet = TimeSheet::new(String::from("test"), None);
+        assert_eq!(time_sheet.rounding_mode(), RoundingMode::Up);
+        assert!(!time_sheet.default_homeoffice());
+        time_sheet.defaults = toml::from_str(
+            "rounding_mode = \"nearest\"\ndefault_homeoffice = true\ncurrency = \"USD\"",
+        )
+        .unwrap();
+        assert_eq!(time_sheet.rounding_mode(), RoundingMode::Nearest);
+        assert!(time_sheet.default_homeoffice());
+        time_sheet.rounding_mode = Some(RoundingMode::Down);
+        time_sheet.default_homeoffice = Some(false);
+        assert_eq!(time_sheet.rounding_mode(), RoundingMode::Down);
+        assert!(!time_sheet.default_homeoffice());
+        assert!(toml::from_str::<GlobalConfig>("currencies = \"USD\"").is_err());
+        assert!(check_datetime_format("%d.%m.%Y %H:%M").is_ok());
+        assert!(check_datetime_format("%Y-%m-%d %Q").is_err());
+    }
+
+    #[test]
+    fn test_set_global_config() {
+        let path = temp_path("global");
+        set_global_config(GlobalConfig {
+            hourly_rate: Some(42.),
+            default_homeoffice: Some(true),
+            datetime_format: Some(String::from("%d.%m.%Y %H:%M")),
+            ..Default::default()
+        });
+        initialize_project(String::from("test"), None, true, true, &path).unwrap();
+        let time_sheet = TimeSheet::load(&path).unwrap();
+        assert_eq!(
+            time_sheet.rate_at(Local::now().naive_local().date()),
+            Some(42.)
+        );
+        assert!(time_sheet.default_homeoffice());
+        assert_eq!(time_sheet.datetime_format(), "%d.%m.%Y %H:%M");
+        set_global_config(GlobalConfig::default());
+        assert!(!TimeSheet::load(&path).unwrap().default_homeoffice());
+    }
+
+    #[test]
+    fn test_seconds_precision() {
+        let time = parse_datetime("2020-01-01 09:00:30").unwrap();
+        assert_eq!(time.second(), 30);
+        assert_eq!(
+            parse_datetime("2020-01-01 09:00").unwrap(),
+            time - chrono::Duration::seconds(30)
+        );
+        assert!(parse_datetime("2020-01-01").is_err());
+
+        let options = AnalyzeOptions {
+            seconds: true,
+            ..Default::default()
+        };
+        assert_eq!(
+            format_duration(chrono::Duration::seconds(3725), &options),
+            "1:02:05"
+        );
+        assert_eq!(hours(chrono::Duration::seconds(90)), 0.025);
+    }
+
+    #[test]
+    fn test_start_stop_minute_aligned() {
+        let path = temp_path("minute");
+        initialize_project(String::from("test"), None, true, true, &path).unwrap();
+        start_working_session(None, None, None, None, None, true, false, &path).unwrap();
+        stop_working_session(None, None, None, None, false, &path).unwrap();
+        let time_sheet = TimeSheet::load(&path).unwrap();
+        let work_session = &time_sheet.work_sessions()[0];
+        let stop = work_session.stop().unwrap();
+        for time in &[work_session.start(), stop] {
+            assert_eq!(time.second(), 0);
+            assert_eq!(time.nanosecond(), 0);
+        }
+        assert_eq!(work_session.duration(Local::now()).num_seconds() % 60, 0);
+    }
+
+    #[test]
+    fn test_export_grouped_by_subproject() {
+        let mut time_sheet = sheet_with(&[
+            ("2020-01-01 09:00", Some("2020-01-01 10:00")),
+            ("2020-01-01 10:00", Some("2020-01-01 10:30")),
+            ("2020-01-01 11:00", Some("2020-01-01 12:30")),
+        ]);
+        time_sheet
+            .subprojects
+            .push(SubProject::new(0, String::from("api"), String::new()));
+        time_sheet.work_sessions[0].subproject = Some(0);
+        time_sheet.work_sessions[2].subproject = Some(0);
+        let sections = export_sections(&time_sheet, true);
+        let titles: Vec<&str> = sections.iter().map(|(t, _)| t.as_str()).collect();
+        assert_eq!(titles, vec!["api", "unassigned"]);
+
+        let mut csv_output = Vec::new();
+        write_csv_export(
+            &mut csv_output,
+            &sections,
+            true,
+            DATETIME_FORMAT,
+            Local::now(),
+        )
+        .unwrap();
+        let csv_output = String::from_utf8(csv_output).unwrap();
+        assert!(csv_output.contains("api,,,Subtotal,,2.50\n"));
+        assert!(csv_output.contains("unassigned,,,Subtotal,,0.50\n"));
+
+        let markdown = markdown_export(&sections, true, DATETIME_FORMAT, Local::now());
+        assert!(markdown.starts_with("## api\n"));
+        assert!(markdown.contains("| | | **Subtotal** | | **2.50** |"));
+    }
+
+    #[test]
+    fn test_stored_time_formats() {
+        let work_session = WorkSession::new(
+            at("2020-01-01 09:00"),
+            Some(at("2020-01-01 10:00")),
+            String::new(),
+            false,
+        );
+        let json = serde_json::to_string(&work_session).unwrap();
+        assert!(json.contains(&format!(
+            "\"start\":\"{}\"",
+            at("2020-01-01 09:00").to_rfc3339()
+        )));
+        let parsed: WorkSession = serde_json::from_str(&json).unwrap();
+        assert_eq!(parsed, work_session);
+
+        let legacy: WorkSession = serde_json::from_str(
+            r#"{"start":"2020-01-01 09:00","stop":"2020-01-01T10:00:00.123456789Z","description":""}"#,
+        )
+        .unwrap();
+        assert_eq!(legacy.start, at("2020-01-01 09:00"));
+        assert_eq!(
+            legacy.stop,
+            Some(
+                DateTime::parse_from_rfc3339("2020-01-01T10:00:00.123456789Z")
+                    .unwrap()
+                    .with_timezone(&Local)
+            )
+        );
+        let open: WorkSession =
+            serde_json::from_str(r#"{"start":"2020-01-01T09:00:00+01:00","description":""}"#)
+                .unwrap();
+        assert_eq!(open.stop, None);
+        assert!(
+            serde_json::from_str::<WorkSession>(r#"{"start":"soon","description":""}"#).is_err()
+        );
+    }
+
+    #[test]
+    fn test_session_stats() {
+        assert_eq!(sheet_with(&[]).session_stats(Local::now()), None);
+        let time_sheet = sheet_with(&[
+            ("2020-01-01 09:00", Some("2020-01-01 10:00")),
+            ("2020-01-02 09:00", Some("2020-01-02 09:30")),
+            ("2020-01-03 09:00", Some("2020-01-03 12:00")),
+            ("2020-01-04 09:00", Some("2020-01-04 11:00")),
+        ]);
+        let stats = time_sheet.session_stats(Local::now()).unwrap();
+        assert_eq!(
+            stats.mean,
+            chrono::Duration::minutes(97) + chrono::Duration::seconds(30)
+        );
+        assert_eq!(stats.median, chrono::Duration::minutes(90));
+        assert_eq!(
+            stats.longest,
+            (chrono::Duration::hours(3), at("2020-01-03 09:00"))
+        );
+        assert_eq!(
+            stats.shortest,
+            (chrono::Duration::minutes(30), at("2020-01-02 09:00"))
+        );
+    }
+
+    #[test]
+    fn test_snap_to_grid() {
+        assert_eq!(
+            snap_to_grid(at("2020-01-01 10:07"), 15),
+            at("2020-01-01 10:00")
+        );
+        assert_eq!(
+            snap_to_grid(at("2020-01-01 10:08"), 15),
+            at("2020-01-01 10:15")
+        );
+        assert_eq!(
+            snap_to_grid(at("2020-01-01 23:53"), 15),
+            at("2020-01-02 00:00")
+        );
+        assert_eq!(
+            snap_to_grid(at("2020-01-01 10:08"), 1),
+            at("2020-01-01 10:08")
+        );
+    }
+
+    #[test]
+    fn test_read_csv_work_sessions() {
+        let data = "start,stop,description,homeoffice\n\
+                    2020-01-01 09:00,2020-01-01 12:00,first,true\n\
+                    2020-01-02 09:00,,second,false\n";
+        let work_sessions = read_csv_work_sessions(data.as_bytes(), DATETIME_FORMAT).unwrap();
+        assert_eq!(work_sessions.len(), 2);
+        assert!(work_sessions[0].homeoffice);
+        assert_eq!(work_sessions[1].stop, None);
+
+        let data = "start,stop,description,homeoffice\n\
+                    2020-01-01 09:00,2020-01-01 12:00,first,true\n\
+                    2020-01-02 09:00,not a date,second,false\n";
+        match read_csv_work_sessions(data.as_bytes(), DATETIME_FORMAT) {
+            Err(TimetrackerError::Csv(e)) => assert!(e.contains("line 3")),
+            r => panic!("Unexpected result: {:?}", r),
+        }
+    }
+
+    #[test]
+    fn test_add_expense() {
+        let path = temp_path("expense");
+        initialize_project(String::from("test"), None, true, true, &path).unwrap();
+        add_expense(Some("2020-01-02"), 12.5, "train", &path).unwrap();
+        add_expense(Some("2020-01-01"), 3., "coffee", &path).unwrap();
+        match add_expense(None, -1., "refund", &path) {
+            Err(TimetrackerError::TimeSheet(e)) => assert!(e.contains("amount")),
+            r => panic!("Unexpected result: {:?}", r),
+        }
+        let time_sheet = TimeSheet::load(&path).unwrap();
+        let descriptions: Vec<&str> = time_sheet
+            .expenses()
+            .iter()
+            .map(|expense| expense.description())
+            .collect();
+        assert_eq!(descriptions, vec!["coffee", "train"]);
+    }
+
+    #[test]
+    fn test_hour_durations() {
+        let time_sheet = sheet_with(&[
+            ("2020-01-01 09:45", Some("2020-01-01 11:30")),
+            ("2020-01-01 23:30", Some("2020-01-02 00:15")),
+        ]);
+        let hours = time_sheet.hour_durations(at("2020-01-02 12:00"));
+        assert_eq!(hours[9], chrono::Duration::minutes(15));
+        assert_eq!(hours[10], chrono::Duration::minutes(60));
+        assert_eq!(hours[11], chrono::Duration::minutes(30));
+        assert_eq!(hours[23], chrono::Duration::minutes(30));
+        assert_eq!(hours[0], chrono::Duration::minutes(15));
+        assert_eq!(hours[12], chrono::Duration::zero());
+    }
+
+    #[test]
+    fn test_escape_tsv() {
+        assert_eq!(escape_tsv("plain"), "plain");
+        assert_eq!(escape_tsv("a\tb\nc\\d"), "a\\tb\\nc\\\\d");
+    }
+
+    #[test]
+    fn test_future_start() {
+        let path = temp_path("future");
+        initialize_project(String::from("test"), None, true, true, &path).unwrap();
+        let tomorrow = (Local::now() + chrono::Duration::days(1))
+            .format(DATETIME_FORMAT)
+            .to_string();
+        match add_work_session_to_time_sheet(
+            None, &tomorrow, None, None, None, None, None, true, None, None, &path,
+        ) {
+            Err(TimetrackerError::TimeSheet(e)) => assert!(e.contains("future")),
+            r => panic!("Unexpected result: {:?}", r),
+        }
+        assert!(TimeSheet::load(&path).unwrap().work_sessions.is_empty());
+        match start_working_session(None, None, Some(&tomorrow), None, None, true, true, &path) {
+            Err(TimetrackerError::TimeSheet(e)) => assert!(e.contains("future")),
+            r => panic!("Unexpected result: {:?}", r),
+        }
+        assert!(TimeSheet::load(&path).unwrap().work_sessions.is_empty());
+        add_work_session_to_time_sheet(
+            None,
+            "2020-01-01 09:00",
+            Some("2020-01-01 10:00"),
+            None,
+            None,
+            None,
+            None,
+            true,
+            None,
+            None,
+            &path,
+        )
+        .unwrap();
+        match edit_work_session(0, Some(&tomorrow), None, None, None, None, None, &path) {
+            Err(TimetrackerError::TimeSheet(e)) => assert!(e.contains("future")),
+            r => panic!("Unexpected result: {:?}", r),
+        }
+    }
+
+    #[test]
+    fn test_parse_shift() {
+        assert_eq!(parse_shift("+1h").unwrap(), chrono::Duration::hours(1));
+        assert_eq!(parse_shift("-30m").unwrap(), chrono::Duration::minutes(-30));
+        assert_eq!(parse_shift("1h30m").unwrap(), chrono::Duration::minutes(90));
+        for invalid in &["", "+", "1", "1d", "h", "--1h", "99999999999999h", "9999h"] {
+            assert!(
+                parse_shift(invalid).is_err(),
+                "{} should be invalid",
+                invalid
+            );
+        }
+    }
+
+    #[test]
+    fn test_daily_durations() {
+        let date = |s: &str| NaiveDate::parse_from_str(s, DATE_FORMAT).unwrap();
+        let time_sheet = sheet_with(&[
+            ("2020-01-01 09:00", Some("2020-01-01 10:00")),
+            ("2020-01-01 23:00", Some("2020-01-02 01:00")),
+        ]);
+        let days = time_sheet.daily_durations(at("2020-01-03 00:00"));
+        assert_eq!(days[&date("2020-01-01")], chrono::Duration::hours(2));
+        assert_eq!(days[&date("2020-01-02")], chrono::Duration::hours(1));
+        assert_eq!(days.len(), 2);
+        assert_eq!(parse_month("2024-03").unwrap(), date("2024-03-01"));
+        assert!(parse_month("2024-13").is_err());
+    }
+
+    #[test]
+    fn test_format_total() {
+        let options = AnalyzeOptions {
+            totals_hm: true,
+            ..Default::default()
+        };
+        assert_eq!(
+            format_total(chrono::Duration::minutes(23 * 60 + 30), &options),
+            "23h 30m"
+        );
+        assert_eq!(
+            format_total(chrono::Duration::minutes(-5), &options),
+            "-0h 5m"
+        );
+        assert_eq!(
+            format_total(chrono::Duration::minutes(90), &AnalyzeOptions::default()),
+            "1.50h"
+        );
+    }
+
+    #[test]
+    fn test_switch_keeps_billable() {
+        let path = temp_path("switch");
+        initialize_project(String::from("test"), None, true, true, &path).unwrap();
+        start_working_session(None, None, None, None, None, false, false, &path).unwrap();
+        switch_working_sessions(None, None, &path).unwrap();
+        let time_sheet = TimeSheet::load(&path).unwrap();
+        assert_eq!(time_sheet.work_sessions.len(), 2);
+        assert!(time_sheet.work_sessions.iter().all(|w| !w.billable));
+    }
+
+    #[test]
+    fn test_confirm() {
+        let answer = |input: &str, assume_yes: bool| {
+            confirm("Go on?", &mut input.as_bytes(), assume_yes).is_ok()
+        };
+        assert!(answer("y\n", false));
+        assert!(answer("Yes\n", false));
+        assert!(!answer("n\n", false));
+        assert!(!answer("\n", false));
+        assert!(!answer("", false));
+        assert!(answer("", true));
+    }
+
+    #[test]
+    fn test_total_due() {
+        let now = at("2020-01-02 12:00");
+        let options = AnalyzeOptions::default();
+        match total_due(sheet_with(&[]), &options, now) {
+            Err(TimetrackerError::TimeSheet(e)) => assert!(e.contains("rate")),
+            r => panic!("Unexpected result: {:?}", r),
+        }
+        let mut time_sheet = sheet_with(&[
+            ("2019-12-31 09:00", Some("2019-12-31 17:00")),
+            ("2020-01-01 09:00", Some("2020-01-01 10:30")),
+            ("2020-01-01 11:00", Some("2020-01-01 12:00")),
+            ("2020-01-02 09:00", None),
+        ]);
+        time_sheet.change_rate(INITIAL_RATE_DATE, 10.);
+        time_sheet.work_sessions[0].invoiced = true;
+        time_sheet.work_sessions[2].billable = false;
+        assert_eq!(
+            total_due(time_sheet, &options, now).unwrap(),
+            "Total due: 15.00€ (1.50h @ 10.00€)"
+        );
+    }
+
+    #[test]
+    fn test_discard_work_session() {
+        let path = temp_path("discard");
+        sheet_with(&[("2020-01-01 09:00", Some("2020-01-01 10:00"))])
+            .save(&path)
+            .unwrap();
+        match discard_work_session(&path) {
+            Err(TimetrackerError::TimeSheet(e)) => assert!(e.contains("No unfinished")),
+            r => panic!("Unexpected result: {:?}", r),
+        }
+        sheet_with(&[
+            ("2020-01-01 09:00", Some("2020-01-01 10:00")),
+            ("2020-01-01 11:00", None),
+        ])
+        .save(&path)
+        .unwrap();
+        discard_work_session(&path).unwrap();
+        let time_sheet = TimeSheet::load(&path).unwrap();
+        assert_eq!(time_sheet.work_sessions.len(), 1);
+        assert_eq!(time_sheet.work_sessions[0].start, at("2020-01-01 09:00"));
+    }
+
+    #[test]
+    fn test_resume_last_work_session() {
+        let path = temp_path("resume");
+        sheet_with(&[]).save(&path).unwrap();
+        assert!(resume_last_work_session(&path).is_err());
+        sheet_with(&[
+            ("2020-01-01 09:00", Some("2020-01-01 10:00")),
+            ("2020-01-01 11:00", Some("2020-01-01 12:00")),
+        ])
+        .save(&path)
+        .unwrap();
+        resume_last_work_session(&path).unwrap();
+        let stops: Vec<Option<DateTime<Local>>> = TimeSheet::load(&path)
+            .unwrap()
+            .work_sessions
+            .iter()
+            .map(|w| w.stop)
+            .collect();
+        assert_eq!(stops, vec![Some(at("2020-01-01 10:00")), None]);
+        match resume_last_work_session(&path) {
+            Err(TimetrackerError::TimeSheet(e)) => assert!(e.contains("not finished")),
+            r => panic!("Unexpected result: {:?}", r),
+        }
+    }
+
+    #[test]
+    fn test_reconciled_durations() {
+        let minutes = chrono::Duration::minutes;
+        let mut time_sheet = sheet_with(&[
+            ("2020-01-01 09:00", Some("2020-01-01 09:07")),
+            ("2020-01-01 10:00", Some("2020-01-01 10:30")),
+            ("2020-01-01 11:00", Some("2020-01-01 11:05")),
+        ]);
+        time_sheet.billing_increment_minutes = Some(15);
+        time_sheet.work_sessions[2].billable = false;
+        let durations: Vec<(usize, chrono::Duration, chrono::Duration)> = time_sheet
+            .reconciled_durations(Local::now())
+            .into_iter()
+            .map(|(work_session, tracked, billed)| (work_session.id, tracked, billed))
+            .collect();
+        assert_eq!(
+            durations,
+            vec![(0, minutes(7), minutes(15)), (1, minutes(30), minutes(30))]
+        );
+    }
+
+    #[test]
+    fn test_merge_sessions() {
+        let path = temp_path("merge");
+        let adjacent = || {
+            let mut time_sheet = sheet_with(&[
+                ("2020-01-01 09:00", Some("2020-01-01 10:00")),
+                ("2020-01-01 10:00", Some("2020-01-01 11:00")),
+            ]);
+            time_sheet.work_sessions[0].subproject = Some(0);
+            time_sheet.work_sessions[0].tags = vec![String::from("client")];
+            time_sheet
+        };
+        for conflict in &["billable", "invoiced", "subprojects"] {
+            let mut time_sheet = adjacent();
+            let second = &mut time_sheet.work_sessions[1];
+            match *conflict {
+                "billable" => second.billable = false,
+                "invoiced" => second.invoiced = true,
+                _ => second.subproject = Some(1),
+            }
+            time_sheet.save(&path).unwrap();
+            match merge_sessions(0, 1, &path) {
+                Err(TimetrackerError::TimeSheet(e)) => assert!(e.contains(conflict)),
+                r => panic!("Unexpected result: {:?}", r),
+            }
+            assert_eq!(TimeSheet::load(&path).unwrap().work_sessions.len(), 2);
+        }
+
+        let mut time_sheet = adjacent();
+        time_sheet.work_sessions[1].tags = vec![String::from("client"), String::from("travel")];
+        time_sheet.save(&path).unwrap();
+        merge_sessions(1, 0, &path).unwrap();
+        let time_sheet = TimeSheet::load(&path).unwrap();
+        assert_eq!(time_sheet.work_sessions.len(), 1);
+        let work_session = &time_sheet.work_sessions[0];
+        assert_eq!(work_session.stop, Some(at("2020-01-01 11:00")));
+        assert_eq!(work_session.subproject, Some(0));
+        assert_eq!(work_session.tags, vec!["client", "travel"]);
+    }
+
+    #[test]
+    fn test_split_session() {
+        let path = temp_path("split");
+        let mut time_sheet = sheet_with(&[("2020-01-01 09:00", Some("2020-01-01 11:00"))]);
+        let work_session = &mut time_sheet.work_sessions[0];
+        work_session.description = String::from("review");
+        work_session.homeoffice = true;
+        work_session.activity = Some(0);
+        work_session.subproject = Some(1);
+        work_session.billable = false;
+        work_session.fixed_fee = Some(50.);
+        work_session.tags = vec![String::from("client")];
+        work_session.invoiced = true;
+        time_sheet.save(&path).unwrap();
+        assert!(split_session(0, "2020-01-01 11:00", &path).is_err());
+        split_session(0, "2020-01-01 10:00", &path).unwrap();
+
+        let time_sheet = TimeSheet::load(&path).unwrap();
+        let (first, second) = (&time_sheet.work_sessions[0], &time_sheet.work_sessions[1]);
+        assert_eq!(
+            (first.id, first.start, first.stop),
+            (0, at("2020-01-01 09:00"), Some(at("2020-01-01 10:00")))
+        );
+        assert_eq!(
+            (second.id, second.start, second.stop),
+            (1, at("2020-01-01 10:00"), Some(at("2020-01-01 11:00")))
+        );
+        assert_eq!((first.fixed_fee, second.fixed_fee), (Some(50.), None));
+        for work_session in &[first, second] {
+            assert_eq!(work_session.description, "review");
+            assert!(work_session.homeoffice);
+            assert_eq!(work_session.activity, Some(0));
+            assert_eq!(work_session.subproject, Some(1));
+            assert!(!work_session.billable);
+            assert_eq!(work_session.tags, vec!["client"]);
+            assert!(work_session.invoiced);
         }
     }
 }
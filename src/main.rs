@@ -1,19 +1,18 @@
 // use chrono::prelude::*;
 use chrono::prelude::*;
-use clap::{crate_authors, crate_description, crate_name, crate_version, App, Arg, SubCommand};
+use clap::{
+    crate_authors, crate_description, crate_name, crate_version, App, Arg, ArgGroup, ArgMatches,
+    SubCommand,
+};
 use std::path::Path;
+use timetracker::TimetrackerError;
 
 fn main() {
     let rate_option = Arg::with_name("rate")
         .short("r")
         .long("rate")
         .value_name("RATE")
-        .validator(|s: String| match &s.parse::<f32>() {
-            Ok(_) => Ok(()),
-            Err(_) => Err(String::from(
-                "Must be convertible to floating point number!",
-            )),
-        })
+        .validator(non_negative_number_validator)
         .help("Hourly rate");
 
     let project_argument = Arg::with_name("project")
@@ -27,7 +26,7 @@ fn main() {
         .help("Project to stop work on");
 
     fn time_validator(s: String) -> Result<(), String> {
-        match Local.datetime_from_str(&s, timetracker::DATETIME_FORMAT) {
+        match timetracker::parse_datetime(&s) {
             Ok(_) => Ok(()),
             Err(_) => Err(format!(
                 "Must comply with \"{}\" format!",
@@ -59,36 +58,197 @@ fn main() {
         .validator(time_validator)
         .help(&stop_help_string);
 
+    let at_help_string = format!(
+        "Use the given time instead of now, formatted as \"{}\"",
+        timetracker::DATETIME_FORMAT
+    );
+    let at_option = Arg::with_name("at")
+        .short("a")
+        .long("at")
+        .value_name("TIME")
+        .validator(time_validator)
+        .help(&at_help_string);
+
+    let auto_fix_option = Arg::with_name("auto_fix").long("auto-fix").help(
+        "Stop a work session running longer than configured by --auto-stop-after at that time",
+    );
+
+    let force_at_option = Arg::with_name("force")
+        .long("force")
+        .requires("at")
+        .help("Accept a time given with --at even if it is far from now");
+
+    let split_at_help_string = format!(
+        "Time to split the work session at, formatted as \"{}\"",
+        timetracker::DATETIME_FORMAT
+    );
+
     let description_option = Arg::with_name("description")
         .short("d")
         .long("description")
         .value_name("DESCRIPTION")
         .help("A description of what was done during this work session");
 
+    fn id_validator(s: String) -> Result<(), String> {
+        match &s.parse::<usize>() {
+            Ok(_) => Ok(()),
+            Err(_) => Err(String::from("Must be a unsigned integer!")),
+        }
+    }
+
     let work_session_id_option = Arg::with_name("work_session_id")
         .short("i")
         .long("id")
         .value_name("ID")
         .required(true)
-        .validator(|s: String| match &s.parse::<usize>() {
-            Ok(_) => Ok(()),
-            Err(_) => Err(String::from("Must be a unsigned integer!")),
-        })
+        .validator(id_validator)
         .help("Id of the work session to be edited");
 
+    fn date_validator(s: String) -> Result<(), String> {
+        match NaiveDate::parse_from_str(&s, timetracker::DATE_FORMAT) {
+            Ok(_) => Ok(()),
+            Err(_) => Err(format!(
+                "Must comply with \"{}\" format!",
+                timetracker::DATE_FORMAT
+            )),
+        }
+    }
+
+    fn non_negative_number_validator(s: String) -> Result<(), String> {
+        match s.parse::<f32>() {
+            Ok(f) if f.is_finite() && f >= 0f32 => Ok(()),
+            _ => Err(String::from("Must be a non-negative number!")),
+        }
+    }
+
+    fn positive_number_validator(s: String) -> Result<(), String> {
+        match s.parse::<f32>() {
+            Ok(f) if f.is_finite() && f > 0f32 => Ok(()),
+            _ => Err(String::from("Must be a positive number!")),
+        }
+    }
+
+    fn non_negative_integer_validator(s: String) -> Result<(), String> {
+        match s.parse::<u32>() {
+            Ok(_) => Ok(()),
+            Err(_) => Err(String::from("Must be a non-negative integer!")),
+        }
+    }
+
+    fn positive_integer_validator(s: String) -> Result<(), String> {
+        match s.parse::<u32>() {
+            Ok(n) if n > 0 => Ok(()),
+            _ => Err(String::from("Must be a positive integer!")),
+        }
+    }
+
+    let date_argument = Arg::with_name("date")
+        .value_name("DATE")
+        .validator(date_validator)
+        .help("A date, formatted as YYYY-MM-DD");
+
+    let from_help_string = format!(
+        "First day of the period, formatted as \"{}\"",
+        timetracker::DATE_FORMAT
+    );
+    let to_help_string = format!(
+        "Last day of the period, formatted as \"{}\"",
+        timetracker::DATE_FORMAT
+    );
+    let locale_option = Arg::with_name("locale")
+        .long("locale")
+        .value_name("LOCALE")
+        .possible_values(&["en", "de"])
+        .help("Format numbers as 1234.56 (en) or 1.234,56 (de)");
+
+    let from_option = Arg::with_name("from")
+        .long("from")
+        .value_name("DATE")
+        .required(true)
+        .validator(date_validator)
+        .help(&from_help_string);
+    let to_option = Arg::with_name("to")
+        .long("to")
+        .value_name("DATE")
+        .required(true)
+        .validator(date_validator)
+        .help(&to_help_string);
+
+    let activity_id_option = Arg::with_name("activity_id")
+        .short("i")
+        .long("id")
+        .value_name("ID")
+        .validator(id_validator)
+        .help("Id of the activity");
+
+    let activity_option = Arg::with_name("activity")
+        .long("activity")
+        .value_name("ACTIVITY-ID")
+        .validator(id_validator)
+        .help("Id of the activity worked on");
+
+    let subproject_option = Arg::with_name("subproject")
+        .long("subproject")
+        .value_name("SUBPROJECT-ID")
+        .validator(id_validator)
+        .help("Id of the subproject worked on");
+
+    let parent_option = Arg::with_name("parent")
+        .long("parent")
+        .value_name("SUBPROJECT-ID")
+        .validator(id_validator)
+        .help("Id of the subproject to nest this one in");
+
     let description_argument = Arg::with_name("description")
         .value_name("DESCRIPTION")
-        .help("A description of what you did");
+        .multiple(true)
+        .help("A description of what you did, multiple words are joined by spaces");
+
+    let fee_option = Arg::with_name("fee")
+        .long("fee")
+        .value_name("AMOUNT")
+        .validator(non_negative_number_validator)
+        .help("Fixed fee billed on top of the hourly cost, 0 removes it");
+
+    let non_billable_option = Arg::with_name("non_billable")
+        .long("non-billable")
+        .help("Don't bill the work session to the client");
 
     let homeoffice_option = Arg::with_name("homeoffice")
         .short("h")
         .long("homeoffice")
         .help("Track whether a day was spend in homeoffice or not");
 
+    let no_homeoffice_option = Arg::with_name("no_homeoffice")
+        .long("no-homeoffice")
+        .conflicts_with("homeoffice")
+        .help("Track that a work session was not spent in homeoffice");
+
     let matches = App::new(crate_name!())
         .version(crate_version!())
         .author(crate_authors!())
         .about(crate_description!())
+        .arg(
+            Arg::with_name("time_sheet")
+                .long("file")
+                .value_name("FILE")
+                .global(true)
+                .help("Time sheet to use, stored as TOML if FILE ends in .toml [default: $TIMETRACKER_FILE or time_sheet.json]"),
+        )
+        .arg(
+            Arg::with_name("verbose")
+                .short("v")
+                .long("verbose")
+                .global(true)
+                .help("Log resolving, loading and saving the time sheet to stderr"),
+        )
+        .arg(
+            Arg::with_name("yes")
+                .short("y")
+                .long("yes")
+                .global(true)
+                .help("Don't ask before overwriting a time sheet or changing many work sessions"),
+        )
         .subcommand(
             SubCommand::with_name("init")
                 .about("Initialize a new project")
@@ -98,76 +258,828 @@ fn main() {
                         .value_name("NAME")
                         .help("Name of the project"),
                 )
-                .arg(&rate_option),
+                .arg(&rate_option)
+                .arg(
+                    Arg::with_name("force")
+                        .long("force")
+                        .help("Overwrite an existing time sheet"),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("start")
+                .about("Start working")
+                .author(crate_authors!())
+                .version(crate_version!())
+                .arg(&project_option)
+                .arg(&homeoffice_option)
+                .arg(&no_homeoffice_option)
+                .arg(&at_option)
+                .arg(&force_at_option)
+                .arg(&activity_option)
+                .arg(&subproject_option)
+                .arg(&non_billable_option)
+                .arg(
+                    Arg::with_name("resume_last")
+                        .long("resume-last")
+                        .conflicts_with_all(&[
+                            "homeoffice",
+                            "no_homeoffice",
+                            "at",
+                            "activity",
+                            "subproject",
+                            "non_billable",
+                            "description",
+                        ])
+                        .help("Continue the most recent work session instead of starting a new one"),
+                )
+                .arg(&description_argument),
+        )
+        .subcommand(
+            SubCommand::with_name("stop")
+                .about("Stop working")
+                .author(crate_authors!())
+                .version(crate_version!())
+                .arg(&project_option)
+                .arg(&homeoffice_option)
+                .arg(&no_homeoffice_option)
+                .arg(&at_option)
+                .arg(&force_at_option)
+                .arg(&activity_option)
+                .arg(
+                    Arg::with_name("discard")
+                        .long("discard")
+                        .conflicts_with_all(&[
+                            "homeoffice",
+                            "no_homeoffice",
+                            "at",
+                            "force",
+                            "activity",
+                            "description",
+                        ])
+                        .help("Throw away the current work session instead of stopping it"),
+                )
+                .arg(&description_argument),
+        )
+        .subcommand(
+            SubCommand::with_name("archive")
+                .about("Move all work sessions to an archive file, keeping the project settings")
+                .author(crate_authors!())
+                .version(crate_version!())
+                .arg(
+                    Arg::with_name("file")
+                        .required(true)
+                        .value_name("FILE")
+                        .help("File to write the archived time sheet to"),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("rename")
+                .about("Rename the project")
+                .author(crate_authors!())
+                .version(crate_version!())
+                .arg(
+                    Arg::with_name("name")
+                        .required(true)
+                        .value_name("NAME")
+                        .help("New name of the project"),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("config")
+                .about("Change settings for a given project")
+                .author(crate_authors!())
+                .version(crate_version!())
+                .arg(&project_argument)
+                .arg(&rate_option)
+                .arg(
+                    Arg::with_name("working_days")
+                        .long("working-days")
+                        .value_name("DAYS")
+                        .validator(|s: String| match &s.parse::<u8>() {
+                            Ok(1..=7) => Ok(()),
+                            _ => Err(String::from("Must be a number between 1 and 7!")),
+                        })
+                        .help("Number of working days per week"),
+                )
+                .arg(
+                    Arg::with_name("minimum_session")
+                        .long("minimum-session")
+                        .value_name("MINUTES")
+                        .validator(non_negative_integer_validator)
+                        .help("Refuse to stop work sessions shorter than MINUTES, 0 disables it"),
+                )
+                .arg(
+                    Arg::with_name("budget")
+                        .long("budget")
+                        .value_name("AMOUNT")
+                        .validator(non_negative_number_validator)
+                        .help("Total amount the project may cost, 0 removes the budget"),
+                )
+                .arg(
+                    Arg::with_name("billing_increment")
+                        .long("billing-increment")
+                        .value_name("MINUTES")
+                        .validator(non_negative_integer_validator)
+                        .help("Bill work sessions in multiples of MINUTES, 0 disables it"),
+                )
+                .arg(
+                    Arg::with_name("rounding")
+                        .long("rounding")
+                        .value_name("MODE")
+                        .possible_values(&["up", "down", "nearest"])
+                        .help("How work sessions are rounded to the billing increment, defaults to up"),
+                )
+                .arg(
+                    Arg::with_name("auto_stop_after")
+                        .long("auto-stop-after")
+                        .value_name("HOURS")
+                        .validator(non_negative_number_validator)
+                        .help("Warn about work sessions running longer than HOURS, 0 disables it"),
+                )
+                .arg(
+                    Arg::with_name("estimate")
+                        .long("estimate")
+                        .value_name("HOURS")
+                        .validator(non_negative_number_validator)
+                        .help("Hours the project is expected to take, 0 removes the estimate"),
+                )
+                .arg(
+                    Arg::with_name("default_homeoffice")
+                        .long("default-homeoffice")
+                        .value_name("BOOL")
+                        .possible_values(&["true", "false"])
+                        .help("Whether new work sessions are done in homeoffice unless --homeoffice or --no-homeoffice is given"),
+                )
+                .arg(
+                    Arg::with_name("max_daily_hours")
+                        .long("max-daily-hours")
+                        .value_name("HOURS")
+                        .validator(|s: String| match &s.parse::<f32>() {
+                            Ok(f) if f.is_finite() && *f > 0f32 && *f <= 24f32 => Ok(()),
+                            _ => Err(String::from("Must be a number between 0 and 24!")),
+                        })
+                        .help("Longest working time per day before analyze --legal-check complains [default: 10]"),
+                )
+                .arg(
+                    Arg::with_name("min_rest_hours")
+                        .long("min-rest-hours")
+                        .value_name("HOURS")
+                        .validator(non_negative_number_validator)
+                        .help("Shortest rest between working days before analyze --legal-check complains [default: 11]"),
+                )
+                .arg(
+                    Arg::with_name("homeoffice_day_threshold")
+                        .long("homeoffice-day-threshold")
+                        .value_name("FRACTION")
+                        .validator(|s: String| match &s.parse::<f32>() {
+                            Ok(f) if (0f32..1f32).contains(f) => Ok(()),
+                            _ => Err(String::from("Must be a number from 0 to less than 1!")),
+                        })
+                        .help("Count days with more than this share of homeoffice time as homeoffice days [default: 0.5]"),
+                )
+                .arg(
+                    Arg::with_name("seconds_precision")
+                        .long("seconds-precision")
+                        .value_name("BOOL")
+                        .possible_values(&["true", "false"])
+                        .help("Track start and stop times to the second, at the cost of less round totals for billing"),
+                )
+                .arg(
+                    Arg::with_name("fixed_price")
+                        .long("fixed-price")
+                        .value_name("AMOUNT")
+                        .validator(non_negative_number_validator)
+                        .help("Flat sum billed for the project instead of the hourly costs, 0 removes it"),
+                )
+                .arg(
+                    Arg::with_name("since")
+                        .long("since")
+                        .value_name("DATE")
+                        .requires("rate")
+                        .validator(date_validator)
+                        .help("Let the new rate take effect on DATE instead of replacing the initial rate"),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("analyze")
+                .about("Analyze all tracked time for a given project")
+                .author(crate_authors!())
+                .version(crate_version!())
+                .arg(&project_argument)
+                .arg(
+                    Arg::with_name("json")
+                        .long("json")
+                        .help("Print a summary as JSON instead of tables, same as --format json"),
+                )
+                .arg(
+                    Arg::with_name("output_format")
+                        .long("format")
+                        .value_name("FORMAT")
+                        .possible_values(&["table", "json", "tsv"])
+                        .conflicts_with("json")
+                        .help("How to print the analysis, tsv prints one work session per line [default: table]"),
+                )
+                .arg(
+                    Arg::with_name("closed_only")
+                        .long("closed-only")
+                        .help("Leave out work sessions that are still running"),
+                )
+                .arg(
+                    Arg::with_name("width")
+                        .short("w")
+                        .long("width")
+                        .value_name("WIDTH")
+                        .validator(positive_integer_validator)
+                        .help(
+                            "Width to wrap descriptions at, derived from the terminal by default",
+                        ),
+                )
+                .arg(
+                    Arg::with_name("round_display")
+                        .long("round-display")
+                        .help("Show durations as H:MM instead of decimal hours"),
+                )
+                .arg(
+                    Arg::with_name("hm")
+                        .long("hm")
+                        .help("Show the durations in the totals table as Xh Ym instead of decimal hours"),
+                )
+                .arg(
+                    Arg::with_name("seconds")
+                        .long("seconds")
+                        .help("Show durations as H:MM:SS, the default for time sheets tracking seconds"),
+                )
+                .arg(
+                    Arg::with_name("quiet")
+                        .short("q")
+                        .long("quiet")
+                        .help("Only print the total work time (and cost, if a rate is set)"),
+                )
+                .arg(
+                    Arg::with_name("plain")
+                        .long("plain")
+                        .alias("no-color")
+                        .help("Print ASCII-only tables without borders or colors"),
+                )
+                .arg(
+                    Arg::with_name("currency")
+                        .long("in")
+                        .value_name("CURRENCY")
+                        .requires("exchange_rate")
+                        .help("Show costs in CURRENCY instead of euros"),
+                )
+                .arg(
+                    Arg::with_name("exchange_rate")
+                        .long("fx")
+                        .value_name("RATE")
+                        .requires("currency")
+                        .validator(positive_number_validator)
+                        .help("Amount of CURRENCY per euro"),
+                )
+                .arg(
+                    Arg::with_name("gaps")
+                        .long("gaps")
+                        .help("Show the gaps between work sessions on the same day"),
+                )
+                .arg(
+                    Arg::with_name("legal_check")
+                        .long("legal-check")
+                        .help("Show days exceeding the maximum daily working time or lacking rest"),
+                )
+                .arg(
+                    Arg::with_name("reconcile")
+                        .long("reconcile")
+                        .help("Compare tracked and billed time of every billable work session"),
+                )
+                .arg(
+                    Arg::with_name("gap_threshold")
+                        .long("gap-threshold")
+                        .value_name("MINUTES")
+                        .requires("gaps")
+                        .validator(non_negative_integer_validator)
+                        .help("Highlight gaps of at least MINUTES minutes [default: 30]"),
+                )
+                .arg(
+                    Arg::with_name("precision")
+                        .long("precision")
+                        .value_name("N")
+                        .validator(non_negative_integer_validator)
+                        .help("Number of decimal places for hours and costs [default: 2]"),
+                )
+                .arg(
+                    Arg::with_name("by")
+                        .long("by")
+                        .value_name("CATEGORY")
+                        .possible_values(&["weekday", "hour"])
+                        .help("Only show the time per category"),
+                )
+                .arg(
+                    Arg::with_name("columns")
+                        .long("columns")
+                        .value_name("COLUMNS")
+                        .use_delimiter(true)
+                        .possible_values(&[
+                            "id",
+                            "start",
+                            "stop",
+                            "ho",
+                            "billable",
+                            "time",
+                            "fee",
+                            "cost",
+                            "description",
+                        ])
+                        .help("Comma separated columns of the work session table, in the order to show them"),
+                )
+                .arg(&locale_option)
+                .arg(&auto_fix_option)
+                .arg(
+                    Arg::with_name("tag")
+                        .long("tag")
+                        .value_name("TAG")
+                        .multiple(true)
+                        .number_of_values(1)
+                        .help("Only include work sessions with this tag, can be given multiple times"),
+                )
+                .arg(
+                    Arg::with_name("exclude_tag")
+                        .long("exclude-tag")
+                        .value_name("TAG")
+                        .multiple(true)
+                        .number_of_values(1)
+                        .help("Leave out work sessions with this tag, even if they match --tag"),
+                )
+                .arg(
+                    Arg::with_name("since")
+                        .long("since")
+                        .value_name("PERIOD")
+                        .validator(|s: String| match timetracker::parse_since(&s, Local::now()) {
+                            Ok(_) => Ok(()),
+                            Err(_) => Err(String::from("Must be a number followed by d, w or m!")),
+                        })
+                        .help("Only include work sessions of the last PERIOD, e.g. 7d, 2w or 1m"),
+                )
+                .arg(
+                    Arg::with_name("since_last_invoice")
+                        .long("since-last-invoice")
+                        .help("Only include work sessions and expenses not invoiced yet"),
+                )
+                .arg(
+                    Arg::with_name("homeoffice_only")
+                        .long("homeoffice-only")
+                        .help("Only include work sessions done in homeoffice"),
+                )
+                .arg(
+                    Arg::with_name("office_only")
+                        .long("office-only")
+                        .conflicts_with("homeoffice_only")
+                        .help("Only include work sessions not done in homeoffice"),
+                )
+                .arg(
+                    Arg::with_name("min_duration")
+                        .long("min-duration")
+                        .value_name("MINUTES")
+                        .validator(non_negative_integer_validator)
+                        .help("Leave out work sessions shorter than MINUTES, they don't count towards the totals either"),
+                )
+                .arg(
+                    Arg::with_name("round_total")
+                        .long("round-total")
+                        .value_name("UNIT")
+                        .validator(positive_number_validator)
+                        .help("Round the total project cost to a multiple of UNIT, e.g. 1 for whole euros"),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("today")
+                .about("Show the work sessions of today")
+                .author(crate_authors!())
+                .version(crate_version!()),
+        )
+        .subcommand(
+            SubCommand::with_name("total-due")
+                .about("Print the amount owed for all closed billable work sessions")
+                .author(crate_authors!())
+                .version(crate_version!())
+                .arg(&locale_option),
+        )
+        .subcommand(
+            SubCommand::with_name("invoice")
+                .about("Show everything not invoiced yet and remember the date of this invoice")
+                .author(crate_authors!())
+                .version(crate_version!())
+                .arg(
+                    date_argument
+                        .clone()
+                        .help("Invoice work sessions up to this date, formatted as YYYY-MM-DD, defaults to today"),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("list")
+                .about("List all projects")
+                .author(crate_authors!())
+                .version(crate_version!()),
+        )
+        .subcommand(
+            SubCommand::with_name("append")
+                .about("Append a note to the description of the running work session")
+                .author(crate_authors!())
+                .version(crate_version!())
+                .arg(
+                    Arg::with_name("text")
+                        .required(true)
+                        .value_name("TEXT")
+                        .help("Text to append"),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("note")
+                .about("Show or change the notes of the project")
+                .author(crate_authors!())
+                .version(crate_version!())
+                .arg(
+                    Arg::with_name("append")
+                        .long("append")
+                        .value_name("TEXT")
+                        .help("Add TEXT as a new line to the notes"),
+                )
+                .arg(
+                    Arg::with_name("set")
+                        .long("set")
+                        .value_name("TEXT")
+                        .conflicts_with("append")
+                        .help("Replace the notes with TEXT"),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("log")
+                .about("Show the most recent work sessions")
+                .author(crate_authors!())
+                .version(crate_version!())
+                .arg(
+                    Arg::with_name("count")
+                        .short("n")
+                        .long("count")
+                        .value_name("N")
+                        .default_value("10")
+                        .validator(positive_integer_validator)
+                        .help("Number of work sessions to show"),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("open")
+                .about("List all work sessions that have not been stopped")
+                .author(crate_authors!())
+                .version(crate_version!()),
+        )
+        .subcommand(
+            SubCommand::with_name("status")
+                .about("Show the running work session and the time worked today")
+                .author(crate_authors!())
+                .version(crate_version!())
+                .arg(&auto_fix_option),
+        )
+        .subcommand(
+            SubCommand::with_name("watch")
+                .about("Keep showing the status, updating it until interrupted with Ctrl-C")
+                .author(crate_authors!())
+                .version(crate_version!())
+                .arg(
+                    Arg::with_name("interval")
+                        .short("n")
+                        .long("interval")
+                        .value_name("SECONDS")
+                        .default_value("60")
+                        .validator(positive_integer_validator)
+                        .help("Seconds between updates"),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("tail")
+                .about("Show the latest work session and follow changes to the time sheet until interrupted with Ctrl-C")
+                .author(crate_authors!())
+                .version(crate_version!()),
+        )
+        .subcommand(
+            SubCommand::with_name("dedupe")
+                .about("Find work sessions with the same start, stop and description, regardless of homeoffice")
+                .author(crate_authors!())
+                .version(crate_version!())
+                .arg(
+                    Arg::with_name("fix")
+                        .long("fix")
+                        .help("Remove all but the first of each duplicated work session"),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("doctor")
+                .about("Show where the time sheet is and check whether it can be used")
+                .author(crate_authors!())
+                .version(crate_version!()),
+        )
+        .subcommand(
+            SubCommand::with_name("add")
+                .about("Add a work session to a given project")
+                .author(crate_authors!())
+                .version(crate_version!())
+                .arg(start_option.clone().required(true))
+                .arg(&stop_option)
+                .arg(&description_option)
+                .arg(&homeoffice_option)
+                .arg(&no_homeoffice_option)
+                .arg(&activity_option)
+                .arg(&subproject_option)
+                .arg(&non_billable_option)
+                .arg(&fee_option)
+                .arg(
+                    Arg::with_name("snap")
+                        .long("snap")
+                        .value_name("MINUTES")
+                        .validator(positive_integer_validator)
+                        .help("Round start and stop time to the nearest multiple of MINUTES"),
+                )
+                .arg(&project_argument),
+        )
+        .subcommand(
+            SubCommand::with_name("edit")
+                .about("Edit a work session to a given project")
+                .author(crate_authors!())
+                .version(crate_version!())
+                .arg(&work_session_id_option)
+                .arg(&start_option)
+                .arg(&stop_option)
+                .arg(
+                    Arg::with_name("shift")
+                        .long("shift")
+                        .value_name("OFFSET")
+                        .allow_hyphen_values(true)
+                        .conflicts_with_all(&["start", "stop"])
+                        .validator(|s: String| match timetracker::parse_shift(&s) {
+                            Ok(_) => Ok(()),
+                            Err(e) => Err(e.to_string()),
+                        })
+                        .help("Move start and stop by an offset like +1h, -30m or 1h30m"),
+                )
+                .arg(&description_option)
+                .arg(&homeoffice_option)
+                .arg(&no_homeoffice_option)
+                .arg(&fee_option)
+                .arg(&project_argument),
+        )
+        .subcommand(
+            SubCommand::with_name("switch")
+                .about("Switch from one work session to the next")
+                .author(crate_authors!())
+                .version(crate_version!())
+                .arg(&project_option)
+                .arg(&homeoffice_option)
+                .arg(&no_homeoffice_option)
+                .arg(&description_argument),
+        )
+        .subcommand(
+            SubCommand::with_name("remove")
+                .about("Remove a work session")
+                .author(crate_authors!())
+                .version(crate_version!())
+                .arg(&work_session_id_option),
+        )
+        .subcommand(
+            SubCommand::with_name("tag")
+                .about("Add tags to a work session")
+                .author(crate_authors!())
+                .version(crate_version!())
+                .arg(&work_session_id_option)
+                .arg(
+                    Arg::with_name("remove")
+                        .long("remove")
+                        .help("Remove the tags instead of adding them"),
+                )
+                .arg(
+                    Arg::with_name("tags")
+                        .value_name("TAG")
+                        .required(true)
+                        .multiple(true)
+                        .help("Tags to add"),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("finalize")
+                .about("Stop a specific running work session at the given time")
+                .author(crate_authors!())
+                .version(crate_version!())
+                .arg(&work_session_id_option)
+                .arg(stop_option.clone().required(true)),
+        )
+        .subcommand(
+            SubCommand::with_name("set-homeoffice")
+                .about("Set the homeoffice flag of all work sessions started in a period")
+                .author(crate_authors!())
+                .version(crate_version!())
+                .arg(&from_option)
+                .arg(&to_option)
+                .arg(
+                    Arg::with_name("value")
+                        .long("value")
+                        .value_name("BOOL")
+                        .possible_values(&["true", "false"])
+                        .default_value("true")
+                        .help("Whether the work sessions were done in homeoffice"),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("mark-homeoffice")
+                .about("Mark a work session as done in homeoffice")
+                .author(crate_authors!())
+                .version(crate_version!())
+                .arg(&work_session_id_option),
+        )
+        .subcommand(
+            SubCommand::with_name("unmark-homeoffice")
+                .about("Mark a work session as not done in homeoffice")
+                .author(crate_authors!())
+                .version(crate_version!())
+                .arg(&work_session_id_option),
+        )
+        .subcommand(
+            SubCommand::with_name("merge")
+                .about("Merge two adjacent work sessions into one")
+                .author(crate_authors!())
+                .version(crate_version!())
+                .arg(
+                    Arg::with_name("first_id")
+                        .required(true)
+                        .value_name("ID")
+                        .validator(id_validator)
+                        .help("Id of the first work session"),
+                )
+                .arg(
+                    Arg::with_name("second_id")
+                        .required(true)
+                        .value_name("ID")
+                        .validator(id_validator)
+                        .help("Id of the second work session"),
+                ),
         )
         .subcommand(
-            SubCommand::with_name("start")
-                .about("Start working")
+            SubCommand::with_name("split")
+                .about("Split a work session into two")
                 .author(crate_authors!())
                 .version(crate_version!())
-                .arg(&project_option)
-                .arg(&homeoffice_option)
-                .arg(&description_argument),
+                .arg(
+                    Arg::with_name("id")
+                        .required(true)
+                        .value_name("ID")
+                        .validator(id_validator)
+                        .help("Id of the work session to split"),
+                )
+                .arg(at_option.clone().required(true).help(&split_at_help_string)),
         )
         .subcommand(
-            SubCommand::with_name("stop")
-                .about("Stop working")
+            SubCommand::with_name("import")
+                .about("Import work sessions from a file")
                 .author(crate_authors!())
                 .version(crate_version!())
-                .arg(&project_option)
-                .arg(&homeoffice_option)
-                .arg(&description_argument),
+                .arg(
+                    Arg::with_name("format")
+                        .short("f")
+                        .long("format")
+                        .value_name("FORMAT")
+                        .possible_values(&["csv", "json"])
+                        .default_value("csv")
+                        .help("Format of the file to import, json merges another time sheet"),
+                )
+                .arg(
+                    Arg::with_name("file")
+                        .required(true)
+                        .value_name("FILE")
+                        .help("File to import work sessions from"),
+                ),
         )
         .subcommand(
-            SubCommand::with_name("config")
-                .about("Change settings for a given project")
+            SubCommand::with_name("holidays")
+                .about("Manage holidays excluded from the working days")
                 .author(crate_authors!())
                 .version(crate_version!())
-                .arg(&project_argument)
-                .arg(&rate_option),
+                .subcommand(
+                    SubCommand::with_name("add")
+                        .about("Add a holiday")
+                        .author(crate_authors!())
+                        .version(crate_version!())
+                        .arg(date_argument.clone().required(true)),
+                )
+                .subcommand(
+                    SubCommand::with_name("remove")
+                        .about("Remove a holiday")
+                        .author(crate_authors!())
+                        .version(crate_version!())
+                        .arg(date_argument.clone().required(true)),
+                )
+                .subcommand(
+                    SubCommand::with_name("list")
+                        .about("List all holidays")
+                        .author(crate_authors!())
+                        .version(crate_version!()),
+                ),
         )
         .subcommand(
-            SubCommand::with_name("analyze")
-                .about("Analyze all tracked time for a given project")
+            SubCommand::with_name("expense")
+                .about("Manage expenses billed on top of the tracked time")
                 .author(crate_authors!())
                 .version(crate_version!())
-                .arg(&project_argument),
-        )
-        .subcommand(
-            SubCommand::with_name("list")
-                .about("List all projects")
-                .author(crate_authors!())
-                .version(crate_version!()),
+                .subcommand(
+                    SubCommand::with_name("add")
+                        .about("Add an expense")
+                        .author(crate_authors!())
+                        .version(crate_version!())
+                        .arg(
+                            Arg::with_name("amount")
+                                .short("a")
+                                .long("amount")
+                                .value_name("AMOUNT")
+                                .required(true)
+                                .validator(positive_number_validator)
+                                .help("Amount of money spent"),
+                        )
+                        .arg(
+                            Arg::with_name("description")
+                                .short("d")
+                                .long("description")
+                                .value_name("DESCRIPTION")
+                                .required(true)
+                                .help("What the money was spent on"),
+                        )
+                        .arg(
+                            date_argument
+                                .clone()
+                                .help("Date of the expense, formatted as YYYY-MM-DD, defaults to today"),
+                        ),
+                )
+                .subcommand(
+                    SubCommand::with_name("list")
+                        .about("List all expenses")
+                        .author(crate_authors!())
+                        .version(crate_version!()),
+                ),
         )
         .subcommand(
-            SubCommand::with_name("add")
-                .about("Add a work session to a given project")
+            SubCommand::with_name("calendar")
+                .about("Show the time tracked on every day of a month with a running total")
                 .author(crate_authors!())
                 .version(crate_version!())
-                .arg(start_option.clone().required(true))
-                .arg(&stop_option)
-                .arg(&description_option)
-                .arg(&homeoffice_option)
-                .arg(&project_argument),
+                .arg(
+                    Arg::with_name("month")
+                        .short("m")
+                        .long("month")
+                        .value_name("MONTH")
+                        .validator(|s: String| match timetracker::parse_month(&s) {
+                            Ok(_) => Ok(()),
+                            Err(e) => Err(e.to_string()),
+                        })
+                        .help("Month to show, formatted as YYYY-MM, defaults to the current month"),
+                ),
         )
         .subcommand(
-            SubCommand::with_name("edit")
-                .about("Edit a work session to a given project")
+            SubCommand::with_name("capacity")
+                .about("Compare tracked hours with the available working hours")
                 .author(crate_authors!())
                 .version(crate_version!())
-                .arg(&work_session_id_option)
-                .arg(&start_option)
-                .arg(&stop_option)
-                .arg(&description_option)
-                .arg(&project_argument),
+                .arg(&from_option)
+                .arg(&to_option),
         )
         .subcommand(
-            SubCommand::with_name("switch")
-                .about("Switch from one work session to the next")
+            SubCommand::with_name("export")
+                .about("Export work sessions to a file or stdout")
                 .author(crate_authors!())
                 .version(crate_version!())
-                .arg(&project_option)
-                .arg(&description_argument),
+                .arg(
+                    Arg::with_name("format")
+                        .short("f")
+                        .long("format")
+                        .value_name("FORMAT")
+                        .possible_values(&["ical", "flat-json", "csv", "markdown"])
+                        .required(true)
+                        .help("Format of the exported file"),
+                )
+                .arg(
+                    Arg::with_name("group_by")
+                        .long("group-by")
+                        .value_name("CATEGORY")
+                        .possible_values(&["subproject"])
+                        .help("Split csv and markdown exports into sections with subtotals"),
+                )
+                .arg(
+                    Arg::with_name("output")
+                        .short("o")
+                        .long("output")
+                        .value_name("FILE")
+                        .help("File to export work sessions to"),
+                )
+                .arg(
+                    Arg::with_name("file")
+                        .value_name("FILE")
+                        .conflicts_with("output")
+                        .help("File to export work sessions to, same as --output"),
+                ),
         )
         .subcommand(
             SubCommand::with_name("activities")
@@ -178,19 +1090,51 @@ fn main() {
                     SubCommand::with_name("add")
                         .about("Add a new activity")
                         .author(crate_authors!())
-                        .version(crate_version!()),
+                        .version(crate_version!())
+                        .arg(
+                            Arg::with_name("name")
+                                .short("n")
+                                .long("name")
+                                .value_name("NAME")
+                                .required(true)
+                                .help("A name identifier for a new activity"),
+                        )
+                        .arg(
+                            Arg::with_name("description")
+                                .short("d")
+                                .long("description")
+                                .value_name("DESCRIPTION")
+                                .required(true)
+                                .help("A description for a new activity"),
+                        ),
                 )
                 .subcommand(
                     SubCommand::with_name("remove")
                         .about("Remove a given activity")
                         .author(crate_authors!())
-                        .version(crate_version!()),
+                        .version(crate_version!())
+                        .arg(activity_id_option.clone().required(true)),
                 )
                 .subcommand(
                     SubCommand::with_name("edit")
                         .about("Edit a given activity")
                         .author(crate_authors!())
-                        .version(crate_version!()),
+                        .version(crate_version!())
+                        .arg(activity_id_option.clone().required(true))
+                        .arg(
+                            Arg::with_name("name")
+                                .short("n")
+                                .long("name")
+                                .value_name("NAME")
+                                .help("A new name for the activity"),
+                        )
+                        .arg(
+                            Arg::with_name("description")
+                                .short("d")
+                                .long("description")
+                                .value_name("DESCRIPTION")
+                                .help("A new description for the activity"),
+                        ),
                 )
                 .subcommand(
                     SubCommand::with_name("list")
@@ -224,6 +1168,12 @@ fn main() {
                                 .value_name("DESCRIPTION")
                                 .required(true)
                                 .help("A description for a new subproject"),
+                        )
+                        .arg(&parent_option)
+                        .arg(
+                            rate_option
+                                .clone()
+                                .help("Hourly rate for work sessions of the subproject instead of the project's rate"),
                         ),
                 )
                 .subcommand(
@@ -236,6 +1186,46 @@ fn main() {
                     SubCommand::with_name("edit")
                         .about("Edit a given subproject")
                         .author(crate_authors!())
+                        .version(crate_version!())
+                        .arg(
+                            Arg::with_name("id")
+                                .short("i")
+                                .long("id")
+                                .value_name("ID")
+                                .required(true)
+                                .validator(id_validator)
+                                .help("Id of the subproject"),
+                        )
+                        .arg(
+                            Arg::with_name("name")
+                                .short("n")
+                                .long("name")
+                                .value_name("NAME")
+                                .help("A new name for the subproject"),
+                        )
+                        .arg(&parent_option)
+                        .arg(
+                            Arg::with_name("top_level")
+                                .long("top-level")
+                                .help("Remove the subproject from its parent"),
+                        )
+                        .arg(
+                            rate_option
+                                .clone()
+                                .help("Hourly rate for work sessions of the subproject, 0 falls back to the project's rate"),
+                        )
+                        .group(ArgGroup::with_name("new_parent").args(&["parent", "top_level"]))
+                        .group(
+                            ArgGroup::with_name("changes")
+                                .args(&["name", "parent", "top_level", "rate"])
+                                .multiple(true)
+                                .required(true),
+                        ),
+                )
+                .subcommand(
+                    SubCommand::with_name("renumber")
+                        .about("Repair duplicated or missing subproject IDs by numbering them anew")
+                        .author(crate_authors!())
                         .version(crate_version!()),
                 )
                 .subcommand(
@@ -253,51 +1243,342 @@ fn main() {
         )
         .get_matches();
 
+    timetracker::set_verbose(matches.is_present("verbose"));
+    // doctor reports a broken global configuration itself instead of failing on it
+    match timetracker::GlobalConfig::load() {
+        Ok(config) => timetracker::set_global_config(config),
+        Err(e) if matches.subcommand_name() != Some("doctor") => {
+            eprintln!("{}", e);
+            std::process::exit(e.exit_code());
+        }
+        Err(_) => (),
+    }
+    let time_sheet_path = timetracker::time_sheet_path(
+        matches.value_of("time_sheet"),
+        std::env::var_os(timetracker::TIME_SHEET_ENV_VAR).as_deref(),
+    );
+
+    if let Err(e) = run(&matches, &time_sheet_path) {
+        eprintln!("{}", e);
+        std::process::exit(e.exit_code());
+    }
+}
+
+fn homeoffice_value(matches: &ArgMatches) -> Option<bool> {
+    if matches.is_present("homeoffice") {
+        Some(true)
+    } else if matches.is_present("no_homeoffice") {
+        Some(false)
+    } else {
+        None
+    }
+}
+
+fn locale_value(matches: &ArgMatches) -> Option<timetracker::Locale> {
+    match matches.value_of("locale") {
+        Some("en") => Some(timetracker::Locale::En),
+        Some("de") => Some(timetracker::Locale::De),
+        _ => None,
+    }
+}
+
+fn description_value(matches: &ArgMatches) -> Option<String> {
+    matches
+        .values_of("description")
+        .map(|words| words.collect::<Vec<_>>().join(" "))
+}
+
+/// Dispatch to the subcommand given on the command line
+fn run(matches: &ArgMatches, time_sheet_path: &Path) -> Result<(), TimetrackerError> {
+    let assume_yes = matches.is_present("yes");
+
     if let Some(matches) = matches.subcommand_matches("init") {
-        println!("{:#?}", matches);
-        let rate = match matches.value_of("rate") {
-            Some(r) => Some(r.parse::<f32>().unwrap()),
-            None => None,
-        };
-        let path = Path::new("time_sheet.json");
-        timetracker::initialize_project(matches.value_of("name").unwrap().to_string(), rate, &path)
-            .unwrap();
+        let rate = matches.value_of("rate").map(|r| r.parse::<f32>().unwrap());
+        timetracker::initialize_project(
+            matches.value_of("name").unwrap().to_string(),
+            rate,
+            matches.occurrences_of("force") > 0,
+            assume_yes,
+            time_sheet_path,
+        )?;
     }
 
     if let Some(matches) = matches.subcommand_matches("start") {
-        timetracker::start_working_session(
-            matches.value_of("description"),
-            matches.occurrences_of("homeoffice") > 0,
-        )
-        .unwrap();
+        if matches.is_present("resume_last") {
+            timetracker::resume_last_work_session(time_sheet_path)?;
+        } else {
+            timetracker::start_working_session(
+                description_value(matches).as_deref(),
+                homeoffice_value(matches),
+                matches.value_of("at"),
+                matches.value_of("activity").map(|a| a.parse().unwrap()),
+                matches.value_of("subproject").map(|s| s.parse().unwrap()),
+                matches.occurrences_of("non_billable") == 0,
+                matches.occurrences_of("force") > 0,
+                time_sheet_path,
+            )?;
+        }
     }
 
     if let Some(matches) = matches.subcommand_matches("stop") {
-        timetracker::stop_working_session(
-            matches.value_of("description"),
-            matches.occurrences_of("homeoffice") > 0,
-        )
-        .unwrap();
+        if matches.occurrences_of("discard") > 0 {
+            timetracker::discard_work_session(time_sheet_path)?;
+        } else {
+            timetracker::stop_working_session(
+                description_value(matches).as_deref(),
+                homeoffice_value(matches),
+                matches.value_of("at"),
+                matches.value_of("activity").map(|a| a.parse().unwrap()),
+                matches.occurrences_of("force") > 0,
+                time_sheet_path,
+            )?;
+        }
     }
 
     if let Some(matches) = matches.subcommand_matches("analyze") {
-        timetracker::analyze_work_sheet(matches.value_of("project")).unwrap();
+        let options = timetracker::AnalyzeOptions {
+            json: matches.occurrences_of("json") > 0
+                || matches.value_of("output_format") == Some("json"),
+            tsv: matches.value_of("output_format") == Some("tsv"),
+            closed_only: matches.occurrences_of("closed_only") > 0,
+            description_width: matches.value_of("width").map(|w| w.parse().unwrap()),
+            hours_minutes: matches.occurrences_of("round_display") > 0,
+            totals_hm: matches.occurrences_of("hm") > 0,
+            seconds: matches.occurrences_of("seconds") > 0,
+            quiet: matches.occurrences_of("quiet") > 0,
+            plain: matches.occurrences_of("plain") > 0,
+            currency: matches.value_of("currency").map(String::from),
+            exchange_rate: matches
+                .value_of("exchange_rate")
+                .map(|r| r.parse().unwrap()),
+            gaps: matches.occurrences_of("gaps") > 0,
+            legal_check: matches.occurrences_of("legal_check") > 0,
+            reconcile: matches.occurrences_of("reconcile") > 0,
+            gap_threshold: matches
+                .value_of("gap_threshold")
+                .map(|t| t.parse().unwrap()),
+            precision: matches.value_of("precision").map(|p| p.parse().unwrap()),
+            breakdown: match matches.value_of("by") {
+                Some("weekday") => Some(timetracker::Breakdown::Weekday),
+                Some("hour") => Some(timetracker::Breakdown::Hour),
+                _ => None,
+            },
+            since: match matches.value_of("since") {
+                Some(s) => Some(timetracker::parse_since(s, Local::now())?),
+                None => None,
+            },
+            homeoffice: if matches.occurrences_of("homeoffice_only") > 0 {
+                Some(true)
+            } else if matches.occurrences_of("office_only") > 0 {
+                Some(false)
+            } else {
+                None
+            },
+            min_duration: matches.value_of("min_duration").map(|m| m.parse().unwrap()),
+            round_total: matches.value_of("round_total").map(|r| r.parse().unwrap()),
+            locale: locale_value(matches),
+            auto_fix: matches.occurrences_of("auto_fix") > 0,
+            tags: matches
+                .values_of("tag")
+                .map(|tags| tags.map(String::from).collect())
+                .unwrap_or_default(),
+            excluded_tags: matches
+                .values_of("exclude_tag")
+                .map(|tags| tags.map(String::from).collect())
+                .unwrap_or_default(),
+            since_last_invoice: matches.occurrences_of("since_last_invoice") > 0,
+            columns: matches.values_of("columns").map(|columns| {
+                columns
+                    .map(|column| match column {
+                        "id" => timetracker::Column::Id,
+                        "start" => timetracker::Column::Start,
+                        "stop" => timetracker::Column::Stop,
+                        "ho" => timetracker::Column::Homeoffice,
+                        "billable" => timetracker::Column::Billable,
+                        "time" => timetracker::Column::Time,
+                        "fee" => timetracker::Column::Fee,
+                        "cost" => timetracker::Column::Cost,
+                        "description" => timetracker::Column::Description,
+                        _ => unreachable!(),
+                    })
+                    .collect()
+            }),
+        };
+        timetracker::analyze_work_sheet(matches.value_of("project"), &options, time_sheet_path)?;
+    }
+
+    if let Some(_matches) = matches.subcommand_matches("today") {
+        timetracker::analyze_today(time_sheet_path)?;
+    }
+
+    if let Some(matches) = matches.subcommand_matches("total-due") {
+        timetracker::print_total_due(
+            &timetracker::AnalyzeOptions {
+                locale: locale_value(matches),
+                ..Default::default()
+            },
+            time_sheet_path,
+        )?;
+    }
+
+    if let Some(matches) = matches.subcommand_matches("invoice") {
+        timetracker::create_invoice(matches.value_of("date"), time_sheet_path)?;
     }
 
     if let Some(_matches) = matches.subcommand_matches("list") {
         println!("Subcommand list is not implemented yet.")
     }
 
-    if let Some(_matches) = matches.subcommand_matches("config") {
-        println!("Subcommand config is not implemented yet.")
+    if let Some(matches) = matches.subcommand_matches("append") {
+        timetracker::append_description(matches.value_of("text").unwrap(), time_sheet_path)?;
+    }
+
+    if let Some(matches) = matches.subcommand_matches("log") {
+        timetracker::log_work_sessions(
+            matches.value_of("count").unwrap().parse().unwrap(),
+            time_sheet_path,
+        )?;
+    }
+
+    if let Some(_matches) = matches.subcommand_matches("open") {
+        timetracker::list_open_sessions(time_sheet_path)?;
+    }
+
+    if let Some(matches) = matches.subcommand_matches("status") {
+        timetracker::print_status(matches.occurrences_of("auto_fix") > 0, time_sheet_path)?;
+    }
+
+    if let Some(matches) = matches.subcommand_matches("watch") {
+        timetracker::watch_status(
+            matches.value_of("interval").unwrap().parse().unwrap(),
+            time_sheet_path,
+        )?;
+    }
+
+    if let Some(matches) = matches.subcommand_matches("note") {
+        if let Some(text) = matches.value_of("append") {
+            timetracker::edit_notes(text, true, time_sheet_path)?;
+        } else if let Some(text) = matches.value_of("set") {
+            timetracker::edit_notes(text, false, time_sheet_path)?;
+        } else {
+            timetracker::print_notes(time_sheet_path)?;
+        }
+    }
+
+    if let Some(_matches) = matches.subcommand_matches("tail") {
+        timetracker::tail_work_sessions(time_sheet_path)?;
+    }
+
+    if let Some(matches) = matches.subcommand_matches("dedupe") {
+        timetracker::dedupe_work_sessions(
+            matches.occurrences_of("fix") > 0,
+            assume_yes,
+            time_sheet_path,
+        )?;
+    }
+
+    if let Some(_matches) = matches.subcommand_matches("doctor") {
+        timetracker::doctor(time_sheet_path)?;
+    }
+
+    if let Some(matches) = matches.subcommand_matches("archive") {
+        timetracker::archive(
+            Path::new(matches.value_of("file").unwrap()),
+            assume_yes,
+            time_sheet_path,
+        )?;
+    }
+
+    if let Some(matches) = matches.subcommand_matches("rename") {
+        timetracker::rename_project(matches.value_of("name").unwrap(), time_sheet_path)?;
+    }
+
+    if let Some(matches) = matches.subcommand_matches("config") {
+        let config = timetracker::ProjectConfig {
+            hourly_rate: matches.value_of("rate").map(|r| r.parse().unwrap()),
+            rate_since: matches.value_of("since").map(String::from),
+            working_days_per_week: matches.value_of("working_days").map(|d| d.parse().unwrap()),
+            minimum_session_minutes: matches
+                .value_of("minimum_session")
+                .map(|m| m.parse().unwrap()),
+            budget: matches.value_of("budget").map(|b| b.parse().unwrap()),
+            billing_increment_minutes: matches
+                .value_of("billing_increment")
+                .map(|i| i.parse().unwrap()),
+            rounding_mode: match matches.value_of("rounding") {
+                Some("up") => Some(timetracker::RoundingMode::Up),
+                Some("down") => Some(timetracker::RoundingMode::Down),
+                Some("nearest") => Some(timetracker::RoundingMode::Nearest),
+                _ => None,
+            },
+            auto_stop_after_hours: matches
+                .value_of("auto_stop_after")
+                .map(|h| h.parse().unwrap()),
+            estimated_hours: matches.value_of("estimate").map(|h| h.parse().unwrap()),
+            default_homeoffice: matches
+                .value_of("default_homeoffice")
+                .map(|h| h.parse().unwrap()),
+            max_daily_hours: matches
+                .value_of("max_daily_hours")
+                .map(|h| h.parse().unwrap()),
+            min_rest_hours: matches
+                .value_of("min_rest_hours")
+                .map(|h| h.parse().unwrap()),
+            homeoffice_day_threshold: matches
+                .value_of("homeoffice_day_threshold")
+                .map(|t| t.parse().unwrap()),
+            seconds_precision: matches
+                .value_of("seconds_precision")
+                .map(|s| s.parse().unwrap()),
+            fixed_price: matches.value_of("fixed_price").map(|p| p.parse().unwrap()),
+        };
+        timetracker::configure_project(&config, time_sheet_path)?;
+    }
+
+    if let Some(matches) = matches.subcommand_matches("holidays") {
+        if let Some(matches) = matches.subcommand_matches("add") {
+            timetracker::add_holiday(matches.value_of("date").unwrap(), time_sheet_path)?;
+        }
+        if let Some(matches) = matches.subcommand_matches("remove") {
+            timetracker::remove_holiday(matches.value_of("date").unwrap(), time_sheet_path)?;
+        }
+        if let Some(_matches) = matches.subcommand_matches("list") {
+            timetracker::list_holidays(time_sheet_path)?;
+        }
+    }
+
+    if let Some(matches) = matches.subcommand_matches("expense") {
+        if let Some(matches) = matches.subcommand_matches("add") {
+            timetracker::add_expense(
+                matches.value_of("date"),
+                matches.value_of("amount").unwrap().parse().unwrap(),
+                matches.value_of("description").unwrap(),
+                time_sheet_path,
+            )?;
+        }
+        if let Some(_matches) = matches.subcommand_matches("list") {
+            timetracker::list_expenses(time_sheet_path)?;
+        }
+    }
+
+    if let Some(matches) = matches.subcommand_matches("calendar") {
+        timetracker::calendar_report(matches.value_of("month"), time_sheet_path)?;
+    }
+
+    if let Some(matches) = matches.subcommand_matches("capacity") {
+        timetracker::capacity_report(
+            matches.value_of("from").unwrap(),
+            matches.value_of("to").unwrap(),
+            time_sheet_path,
+        )?;
     }
 
     if let Some(subcommand_matches) = matches.subcommand_matches("switch") {
         timetracker::switch_working_sessions(
-            subcommand_matches.value_of("description"),
-            matches.occurrences_of("homeoffice") > 0,
-        )
-        .unwrap();
+            description_value(subcommand_matches).as_deref(),
+            homeoffice_value(subcommand_matches),
+            time_sheet_path,
+        )?;
     }
 
     if let Some(matches) = matches.subcommand_matches("add") {
@@ -306,27 +1587,169 @@ fn main() {
             matches.value_of("start").unwrap(),
             matches.value_of("stop"),
             matches.value_of("description"),
-            matches.occurrences_of("homeoffice") > 0,
-        )
-        .unwrap();
+            homeoffice_value(matches),
+            matches.value_of("activity").map(|a| a.parse().unwrap()),
+            matches.value_of("subproject").map(|s| s.parse().unwrap()),
+            matches.occurrences_of("non_billable") == 0,
+            matches.value_of("fee").map(|f| f.parse().unwrap()),
+            matches.value_of("snap").map(|s| s.parse().unwrap()),
+            time_sheet_path,
+        )?;
+    }
+
+    if let Some(matches) = matches.subcommand_matches("edit") {
+        timetracker::edit_work_session(
+            matches
+                .value_of("work_session_id")
+                .unwrap()
+                .parse::<usize>()
+                .unwrap(),
+            matches.value_of("start"),
+            matches.value_of("stop"),
+            matches.value_of("shift"),
+            matches.value_of("description"),
+            homeoffice_value(matches),
+            matches.value_of("fee").map(|f| f.parse().unwrap()),
+            time_sheet_path,
+        )?;
+    }
+
+    if let Some(matches) = matches.subcommand_matches("remove") {
+        timetracker::remove_work_session(
+            matches
+                .value_of("work_session_id")
+                .unwrap()
+                .parse::<usize>()
+                .unwrap(),
+            time_sheet_path,
+        )?;
+    }
+
+    if let Some(matches) = matches.subcommand_matches("tag") {
+        timetracker::tag_work_session(
+            matches
+                .value_of("work_session_id")
+                .unwrap()
+                .parse::<usize>()
+                .unwrap(),
+            &matches.values_of("tags").unwrap().collect::<Vec<_>>(),
+            matches.occurrences_of("remove") > 0,
+            time_sheet_path,
+        )?;
+    }
+
+    if let Some(matches) = matches.subcommand_matches("finalize") {
+        timetracker::finalize_work_session(
+            matches
+                .value_of("work_session_id")
+                .unwrap()
+                .parse::<usize>()
+                .unwrap(),
+            matches.value_of("stop").unwrap(),
+            time_sheet_path,
+        )?;
+    }
+
+    if let Some(matches) = matches.subcommand_matches("set-homeoffice") {
+        timetracker::set_homeoffice_between(
+            matches.value_of("from").unwrap(),
+            matches.value_of("to").unwrap(),
+            matches.value_of("value").unwrap().parse::<bool>().unwrap(),
+            assume_yes,
+            time_sheet_path,
+        )?;
+    }
+
+    for (subcommand, homeoffice) in &[("mark-homeoffice", true), ("unmark-homeoffice", false)] {
+        if let Some(matches) = matches.subcommand_matches(subcommand) {
+            timetracker::set_homeoffice(
+                matches
+                    .value_of("work_session_id")
+                    .unwrap()
+                    .parse::<usize>()
+                    .unwrap(),
+                *homeoffice,
+                time_sheet_path,
+            )?;
+        }
+    }
+
+    if let Some(matches) = matches.subcommand_matches("merge") {
+        timetracker::merge_sessions(
+            matches.value_of("first_id").unwrap().parse().unwrap(),
+            matches.value_of("second_id").unwrap().parse().unwrap(),
+            time_sheet_path,
+        )?;
+    }
+
+    if let Some(matches) = matches.subcommand_matches("split") {
+        timetracker::split_session(
+            matches.value_of("id").unwrap().parse().unwrap(),
+            matches.value_of("at").unwrap(),
+            time_sheet_path,
+        )?;
+    }
+
+    if let Some(matches) = matches.subcommand_matches("import") {
+        let path = Path::new(matches.value_of("file").unwrap());
+        match matches.value_of("format") {
+            Some("csv") => timetracker::import_csv(path, assume_yes, time_sheet_path)?,
+            Some("json") => timetracker::merge_time_sheets(path, assume_yes, time_sheet_path)?,
+            _ => unreachable!(),
+        }
     }
 
-    if let Some(_matches) = matches.subcommand_matches("edit") {
-        println!("Subcommand edit is not implemented yet.")
+    if let Some(matches) = matches.subcommand_matches("export") {
+        let path = matches
+            .value_of("output")
+            .or_else(|| matches.value_of("file"))
+            .map(Path::new);
+        let group_by_subproject = matches.value_of("group_by") == Some("subproject");
+        match matches.value_of("format") {
+            Some("ical") => timetracker::export_ical(path, time_sheet_path)?,
+            Some("flat-json") => timetracker::export_flat_json(path, time_sheet_path)?,
+            Some("csv") => timetracker::export_csv(path, group_by_subproject, time_sheet_path)?,
+            Some("markdown") => {
+                timetracker::export_markdown(path, group_by_subproject, time_sheet_path)?
+            }
+            _ => unreachable!(),
+        }
     }
 
     if let Some(matches) = matches.subcommand_matches("activities") {
-        if let Some(_matches) = matches.subcommand_matches("add") {
-            println!("Subcommand add is not implemented yet.")
+        if let Some(matches) = matches.subcommand_matches("add") {
+            timetracker::add_activity(
+                matches.value_of("name").expect("No name given!"),
+                matches
+                    .value_of("description")
+                    .expect("no description given!"),
+                time_sheet_path,
+            )?;
         }
-        if let Some(_matches) = matches.subcommand_matches("remove") {
-            println!("Subcommand remove is not implemented yet.")
+        if let Some(matches) = matches.subcommand_matches("remove") {
+            timetracker::remove_activity(
+                matches
+                    .value_of("activity_id")
+                    .unwrap()
+                    .parse::<usize>()
+                    .unwrap(),
+                time_sheet_path,
+            )?;
         }
-        if let Some(_matches) = matches.subcommand_matches("edit") {
-            println!("Subcommand edit is not implemented yet.")
+        if let Some(matches) = matches.subcommand_matches("edit") {
+            timetracker::edit_activity(
+                matches
+                    .value_of("activity_id")
+                    .unwrap()
+                    .parse::<usize>()
+                    .unwrap(),
+                matches.value_of("name"),
+                matches.value_of("description"),
+                time_sheet_path,
+            )?;
         }
         if let Some(_matches) = matches.subcommand_matches("list") {
-            println!("Subcommand list is not implemented yet.")
+            timetracker::list_activities(time_sheet_path)?;
         }
     }
 
@@ -337,14 +1760,32 @@ fn main() {
                 matches
                     .value_of("description")
                     .expect("no description given!"),
-            )
-            .unwrap();
+                matches.value_of("parent").map(|p| p.parse().unwrap()),
+                matches.value_of("rate").map(|r| r.parse().unwrap()),
+                time_sheet_path,
+            )?;
         }
         if let Some(_matches) = matches.subcommand_matches("remove") {
             println!("Subcommand remove is not implemented yet.")
         }
-        if let Some(_matches) = matches.subcommand_matches("edit") {
-            println!("Subcommand edit is not implemented yet.")
+        if let Some(matches) = matches.subcommand_matches("edit") {
+            let id = matches.value_of("id").unwrap().parse().unwrap();
+            if let Some(name) = matches.value_of("name") {
+                timetracker::rename_subproject(id, name, time_sheet_path)?;
+            }
+            if matches.is_present("new_parent") {
+                timetracker::set_subproject_parent(
+                    id,
+                    matches.value_of("parent").map(|p| p.parse().unwrap()),
+                    time_sheet_path,
+                )?;
+            }
+            if let Some(rate) = matches.value_of("rate") {
+                timetracker::set_subproject_rate(id, rate.parse().unwrap(), time_sheet_path)?;
+            }
+        }
+        if let Some(_matches) = matches.subcommand_matches("renumber") {
+            timetracker::renumber_subprojects(time_sheet_path)?;
         }
         if let Some(_matches) = matches.subcommand_matches("list") {
             println!("Subcommand list is not implemented yet.")
@@ -353,4 +1794,5 @@ fn main() {
             println!("Subcommand export is not implemented yet.")
         }
     }
+    Ok(())
 }